@@ -0,0 +1,402 @@
+// 多点触控手势 - 通过 sendevent 向触摸屏写入多指事件序列（Linux 多点触控协议B）
+// input tap/swipe/motionevent 只能模拟单指，双指缩放、旋转和多指滑动需要直接写触摸事件
+// 设备的 input 命令不支持 draganddrop 时，单指拖动（长按后移动）也通过这里合成
+// - 触摸屏设备及其坐标范围从 getevent -p 中读取，屏幕坐标按比例换算为设备坐标
+// - 每一帧依次写入各手指的 SLOT / TRACKING_ID / POSITION，再写 SYN_REPORT
+// - 整个手势合并成一条 shell 命令执行，避免每个事件一次 adb 往返
+// - 坐标按设备自然方向（通常为竖屏）计算，横屏时触摸坐标系与屏幕坐标不一致
+
+use crate::{Point, Result, TkeError};
+use regex::Regex;
+
+// 事件类型
+const EV_SYN: u32 = 0;
+const EV_KEY: u32 = 1;
+const EV_ABS: u32 = 3;
+
+// 事件代码
+const SYN_REPORT: u32 = 0;
+const BTN_TOUCH: u32 = 0x14a;
+const ABS_MT_SLOT: u32 = 0x2f;
+const ABS_MT_POSITION_X: u32 = 0x35;
+const ABS_MT_POSITION_Y: u32 = 0x36;
+const ABS_MT_TRACKING_ID: u32 = 0x39;
+const ABS_MT_PRESSURE: u32 = 0x3a;
+
+// 手势的帧数（旋转按角度增加帧数，每帧最多转 10 度）
+const GESTURE_STEPS: usize = 10;
+const ROTATE_DEGREES_PER_STEP: f64 = 10.0;
+
+// 双指手势中手指到中心的最小距离（两指过近会被识别为单指）
+pub const MIN_FINGER_RADIUS: i32 = 50;
+// 没有元素范围时（坐标或图像元素）的默认缩放距离和旋转半径
+pub const DEFAULT_PINCH_DISTANCE: i32 = 400;
+pub const DEFAULT_ROTATE_RADIUS: i32 = 150;
+
+// 拖动前的按住时间，超过系统长按阈值（默认 500ms）后目标才会进入拖动状态
+const DRAG_HOLD_MS: u32 = 800;
+// 拖动时每帧的最短间隔
+const MIN_FRAME_MS: u32 = 50;
+
+// 多指滑动时相邻手指的间距（像素）
+pub const FINGER_SPACING: i32 = 80;
+// 多指滑动支持的手指数
+pub const MAX_FINGERS: usize = 5;
+
+// 触摸屏设备信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct TouchDevice {
+    pub path: String,             // 设备节点，如 /dev/input/event2
+    pub x_range: (i32, i32),      // ABS_MT_POSITION_X 的 (min, max)
+    pub y_range: (i32, i32),      // ABS_MT_POSITION_Y 的 (min, max)
+    pub max_slots: Option<usize>, // 支持的同时触点数（ABS_MT_SLOT 的 max + 1）
+    pub pressure: bool,           // 是否需要写入 ABS_MT_PRESSURE
+}
+
+// getevent -p 中的一个输入设备及其绝对坐标轴 (代码, min, max)
+struct InputDevice {
+    path: String,
+    axes: Vec<(u32, i32, i32)>,
+}
+
+impl InputDevice {
+    fn range(&self, code: u32) -> Option<(i32, i32)> {
+        self.axes.iter().find(|(c, _, _)| *c == code).map(|(_, min, max)| (*min, *max))
+    }
+}
+
+// 多指手势: 每一帧中每个手指的屏幕坐标
+#[derive(Debug, Clone)]
+pub struct Gesture {
+    pub frames: Vec<Vec<Point>>,
+    pub duration_ms: u32,
+}
+
+impl Gesture {
+    // 双指捏合/张开: 两指在中心点两侧水平排列，半径从 from_radius 变为 to_radius
+    pub fn pinch(center: Point, from_radius: i32, to_radius: i32, duration_ms: u32) -> Self {
+        let frames = (0..=GESTURE_STEPS)
+            .map(|i| {
+                let radius = from_radius + (to_radius - from_radius) * i as i32 / GESTURE_STEPS as i32;
+                vec![
+                    Point::new(center.x - radius, center.y),
+                    Point::new(center.x + radius, center.y),
+                ]
+            })
+            .collect();
+        Self { frames, duration_ms }
+    }
+
+    // 双指旋转: 两指位于以中心点为圆心、半径为 radius 的圆的两端，转动 degrees 度（正数为顺时针）
+    pub fn rotate(center: Point, radius: i32, degrees: i32, duration_ms: u32) -> Self {
+        let steps = ((degrees.abs() as f64 / ROTATE_DEGREES_PER_STEP).ceil() as usize).max(GESTURE_STEPS);
+        let frames = (0..=steps)
+            .map(|i| {
+                let angle = (degrees as f64 * i as f64 / steps as f64).to_radians();
+                let dx = (radius as f64 * angle.cos()).round() as i32;
+                let dy = (radius as f64 * angle.sin()).round() as i32;
+                vec![
+                    Point::new(center.x - dx, center.y - dy),
+                    Point::new(center.x + dx, center.y + dy),
+                ]
+            })
+            .collect();
+        Self { frames, duration_ms }
+    }
+
+    // 多指滑动: fingers 个手指以起点为中心、垂直于滑动方向等距排列，一起移动到终点
+    pub fn swipe(from: Point, to: Point, fingers: usize, duration_ms: u32) -> Self {
+        let horizontal = (to.x - from.x).abs() > (to.y - from.y).abs();
+        let offsets: Vec<i32> = (0..fingers)
+            .map(|i| (2 * i as i32 - (fingers as i32 - 1)) * FINGER_SPACING / 2)
+            .collect();
+        let frames = (0..=GESTURE_STEPS)
+            .map(|i| {
+                let x = from.x + (to.x - from.x) * i as i32 / GESTURE_STEPS as i32;
+                let y = from.y + (to.y - from.y) * i as i32 / GESTURE_STEPS as i32;
+                offsets.iter()
+                    .map(|offset| if horizontal { Point::new(x, y + offset) } else { Point::new(x + offset, y) })
+                    .collect()
+            })
+            .collect();
+        Self { frames, duration_ms }
+    }
+
+    // 单指拖动: 在起点按住超过长按阈值，再移动到终点后抬起（移动部分用时 duration_ms）
+    pub fn drag(from: Point, to: Point, duration_ms: u32) -> Self {
+        // 各帧间隔相同，按住阶段用重复的起点帧表示
+        let frame_ms = (duration_ms / GESTURE_STEPS as u32).max(MIN_FRAME_MS);
+        let hold_frames = DRAG_HOLD_MS.div_ceil(frame_ms) as usize;
+        let frames: Vec<Vec<Point>> = std::iter::repeat_n(vec![from], hold_frames)
+            .chain((0..=GESTURE_STEPS).map(|i| vec![Point::new(
+                from.x + (to.x - from.x) * i as i32 / GESTURE_STEPS as i32,
+                from.y + (to.y - from.y) * i as i32 / GESTURE_STEPS as i32,
+            )]))
+            .collect();
+        let duration_ms = frame_ms * (frames.len() as u32 - 1);
+        Self { frames, duration_ms }
+    }
+
+    // 手势的手指数
+    pub fn fingers(&self) -> usize {
+        self.frames.first().map_or(0, |frame| frame.len())
+    }
+}
+
+// 从 getevent -p 的输出中找到支持多点触控的触摸屏设备
+// 输出格式:
+//   add device 2: /dev/input/event2
+//     name:     "touchscreen"
+//     events:
+//       ABS (0003): 002f  : value 0, min 0, max 9, fuzz 0, flat 0, resolution 0
+//                   0035  : value 0, min 0, max 1079, fuzz 0, flat 0, resolution 0
+pub fn parse_touch_device(output: &str) -> Option<TouchDevice> {
+    let axis_re = Regex::new(r"\b([0-9a-f]{4})\s*:\s*value -?\d+, min (-?\d+), max (-?\d+)").ok()?;
+
+    let mut devices: Vec<InputDevice> = Vec::new();
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("add device ").and_then(|rest| rest.split(": ").nth(1)) {
+            devices.push(InputDevice { path: path.trim().to_string(), axes: Vec::new() });
+        } else if let (Some(caps), Some(device)) = (axis_re.captures(line), devices.last_mut()) {
+            if let (Ok(code), Ok(min), Ok(max)) = (u32::from_str_radix(&caps[1], 16), caps[2].parse(), caps[3].parse()) {
+                device.axes.push((code, min, max));
+            }
+        }
+    }
+
+    devices.into_iter().find_map(|device| {
+        Some(TouchDevice {
+            x_range: device.range(ABS_MT_POSITION_X)?,
+            y_range: device.range(ABS_MT_POSITION_Y)?,
+            max_slots: device.range(ABS_MT_SLOT).map(|(_, max)| max as usize + 1),
+            pressure: device.range(ABS_MT_PRESSURE).is_some(),
+            path: device.path,
+        })
+    })
+}
+
+// 生成手势对应的 sendevent 命令（; 分隔的一条 shell 命令）
+// screen 为屏幕尺寸 (宽, 高)，用于把屏幕坐标换算为触摸屏坐标
+pub fn sendevent_script(device: &TouchDevice, gesture: &Gesture, screen: (u32, u32)) -> Result<String> {
+    let fingers = gesture.fingers();
+    if fingers == 0 {
+        return Err(TkeError::InvalidArgument("手势至少需要一个手指".to_string()));
+    }
+    if let Some(max_slots) = device.max_slots.filter(|max| fingers > *max) {
+        return Err(TkeError::InvalidArgument(format!("触摸屏最多支持 {} 个触点，手势需要 {} 个", max_slots, fingers)));
+    }
+
+    let scale = |value: i32, size: u32, (min, max): (i32, i32)| -> i32 {
+        let size = size.max(1) as i64;
+        let value = (value as i64).clamp(0, size - 1);
+        (min as i64 + value * (max - min + 1) as i64 / size).min(max as i64) as i32
+    };
+
+    let event = |kind: u32, code: u32, value: i32| format!("sendevent {} {} {} {}", device.path, kind, code, value);
+    let frame_count = gesture.frames.len().max(2) - 1;
+    let frame_delay = gesture.duration_ms as f64 / frame_count as f64 / 1000.0;
+
+    let mut script = Vec::new();
+    for (index, frame) in gesture.frames.iter().enumerate() {
+        for (slot, point) in frame.iter().enumerate() {
+            script.push(event(EV_ABS, ABS_MT_SLOT, slot as i32));
+            if index == 0 {
+                // 每个手指使用不同的跟踪ID
+                script.push(event(EV_ABS, ABS_MT_TRACKING_ID, slot as i32 + 1));
+                if device.pressure {
+                    script.push(event(EV_ABS, ABS_MT_PRESSURE, 50));
+                }
+            }
+            script.push(event(EV_ABS, ABS_MT_POSITION_X, scale(point.x, screen.0, device.x_range)));
+            script.push(event(EV_ABS, ABS_MT_POSITION_Y, scale(point.y, screen.1, device.y_range)));
+        }
+        if index == 0 {
+            script.push(event(EV_KEY, BTN_TOUCH, 1));
+        }
+        script.push(event(EV_SYN, SYN_REPORT, 0));
+        if index + 1 < gesture.frames.len() && frame_delay > 0.0 {
+            script.push(format!("sleep {:.3}", frame_delay));
+        }
+    }
+
+    // 抬起所有手指
+    for slot in 0..fingers {
+        script.push(event(EV_ABS, ABS_MT_SLOT, slot as i32));
+        script.push(event(EV_ABS, ABS_MT_TRACKING_ID, -1));
+    }
+    script.push(event(EV_KEY, BTN_TOUCH, 0));
+    script.push(event(EV_SYN, SYN_REPORT, 0));
+
+    Ok(script.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 真机 getevent -p 的输出（按键、传感器和触摸屏三个设备）
+    const GETEVENT_SAMPLE: &str = "\
+add device 1: /dev/input/event3
+  name:     \"gpio-keys\"
+  events:
+    KEY (0001): 0072  0073  0074
+  input props:
+    <none>
+add device 2: /dev/input/event1
+  name:     \"accelerometer\"
+  events:
+    ABS (0003): 0000  : value -12, min -2048, max 2047, fuzz 0, flat 0, resolution 0
+                0001  : value 30, min -2048, max 2047, fuzz 0, flat 0, resolution 0
+  input props:
+    <none>
+add device 3: /dev/input/event2
+  name:     \"fts_ts\"
+  events:
+    KEY (0001): 008f  014a
+    ABS (0003): 002f  : value 0, min 0, max 9, fuzz 0, flat 0, resolution 0
+                0030  : value 0, min 0, max 255, fuzz 0, flat 0, resolution 0
+                0035  : value 0, min 0, max 4319, fuzz 0, flat 0, resolution 0
+                0036  : value 0, min 0, max 9599, fuzz 0, flat 0, resolution 0
+                0039  : value 0, min 0, max 65535, fuzz 0, flat 0, resolution 0
+  input props:
+    INPUT_PROP_DIRECT
+";
+
+    fn device() -> TouchDevice {
+        parse_touch_device(GETEVENT_SAMPLE).unwrap()
+    }
+
+    #[test]
+    fn parses_touch_device_from_getevent() {
+        assert_eq!(device(), TouchDevice {
+            path: "/dev/input/event2".to_string(),
+            x_range: (0, 4319),
+            y_range: (0, 9599),
+            max_slots: Some(10),
+            pressure: false,
+        });
+    }
+
+    #[test]
+    fn ignores_devices_without_multi_touch_axes() {
+        let without_touch = GETEVENT_SAMPLE.split("add device 3").next().unwrap();
+        assert_eq!(parse_touch_device(without_touch), None);
+        assert_eq!(parse_touch_device(""), None);
+    }
+
+    #[test]
+    fn drag_holds_before_moving() {
+        let gesture = Gesture::drag(Point::new(100, 200), Point::new(600, 200), 500);
+        // 每帧 50ms，按住 800ms 需要 16 帧，移动部分 11 帧
+        assert_eq!(gesture.fingers(), 1);
+        assert_eq!(gesture.frames.len(), 27);
+        let path: Vec<(i32, i32)> = gesture.frames.iter().map(|frame| (frame[0].x, frame[0].y)).collect();
+        assert!(path[..17].iter().all(|point| *point == (100, 200)));
+        assert_eq!(path[21], (350, 200));
+        assert_eq!(path[26], (600, 200));
+        assert_eq!(gesture.duration_ms, 26 * 50);
+    }
+
+    #[test]
+    fn drag_keeps_requested_move_duration() {
+        let gesture = Gesture::drag(Point::new(0, 0), Point::new(0, 1000), 2000);
+        // 每帧 200ms，按住 4 帧，移动 10 个间隔共 2000ms
+        assert_eq!(gesture.frames.len(), 15);
+        assert_eq!(gesture.duration_ms, 14 * 200);
+    }
+
+    #[test]
+    fn sendevent_script_scales_and_releases() {
+        let gesture = Gesture {
+            frames: vec![vec![Point::new(540, 1200)], vec![Point::new(1079, 2399)]],
+            duration_ms: 100,
+        };
+        let script = sendevent_script(&device(), &gesture, (1080, 2400)).unwrap();
+        let commands: Vec<&str> = script.split("; ").collect();
+        assert_eq!(commands, vec![
+            "sendevent /dev/input/event2 3 47 0",
+            "sendevent /dev/input/event2 3 57 1",
+            "sendevent /dev/input/event2 3 53 2160",
+            "sendevent /dev/input/event2 3 54 4800",
+            "sendevent /dev/input/event2 1 330 1",
+            "sendevent /dev/input/event2 0 0 0",
+            "sleep 0.100",
+            "sendevent /dev/input/event2 3 47 0",
+            "sendevent /dev/input/event2 3 53 4316",
+            "sendevent /dev/input/event2 3 54 9596",
+            "sendevent /dev/input/event2 0 0 0",
+            "sendevent /dev/input/event2 3 47 0",
+            "sendevent /dev/input/event2 3 57 -1",
+            "sendevent /dev/input/event2 1 330 0",
+            "sendevent /dev/input/event2 0 0 0",
+        ]);
+    }
+
+    #[test]
+    fn sendevent_script_writes_pressure_when_supported() {
+        let device = TouchDevice { pressure: true, ..device() };
+        let gesture = Gesture::drag(Point::new(10, 10), Point::new(20, 20), 500);
+        let script = sendevent_script(&device, &gesture, (1080, 2400)).unwrap();
+        assert_eq!(script.matches(" 3 58 50").count(), 1);
+        assert_eq!(script.matches("sleep 0.050").count(), gesture.frames.len() - 1);
+    }
+
+    #[test]
+    fn sendevent_script_rejects_empty_gesture() {
+        let gesture = Gesture { frames: Vec::new(), duration_ms: 0 };
+        assert!(sendevent_script(&device(), &gesture, (1080, 2400)).is_err());
+    }
+
+    fn points(frame: &[Point]) -> Vec<(i32, i32)> {
+        frame.iter().map(|point| (point.x, point.y)).collect()
+    }
+
+    #[test]
+    fn pinch_moves_fingers_symmetrically() {
+        let gesture = Gesture::pinch(Point::new(500, 800), 250, MIN_FINGER_RADIUS, 300);
+        assert_eq!(gesture.fingers(), 2);
+        assert_eq!(gesture.frames.len(), GESTURE_STEPS + 1);
+        assert_eq!(points(&gesture.frames[0]), vec![(250, 800), (750, 800)]);
+        assert_eq!(points(&gesture.frames[GESTURE_STEPS]), vec![(450, 800), (550, 800)]);
+    }
+
+    #[test]
+    fn rotate_turns_clockwise_and_adds_steps_for_large_angles() {
+        let gesture = Gesture::rotate(Point::new(500, 500), 100, 90, 500);
+        assert_eq!(gesture.frames.len(), GESTURE_STEPS + 1);
+        assert_eq!(points(&gesture.frames[0]), vec![(400, 500), (600, 500)]);
+        assert_eq!(points(gesture.frames.last().unwrap()), vec![(500, 400), (500, 600)]);
+
+        let gesture = Gesture::rotate(Point::new(500, 500), 100, -360, 500);
+        assert_eq!(gesture.frames.len(), 37);
+        assert_eq!(points(gesture.frames.last().unwrap()), vec![(400, 500), (600, 500)]);
+    }
+
+    #[test]
+    fn swipe_spreads_fingers_across_the_direction() {
+        let gesture = Gesture::swipe(Point::new(500, 1500), Point::new(500, 500), 3, 300);
+        assert_eq!(points(&gesture.frames[0]), vec![(420, 1500), (500, 1500), (580, 1500)]);
+        assert_eq!(points(gesture.frames.last().unwrap()), vec![(420, 500), (500, 500), (580, 500)]);
+
+        let gesture = Gesture::swipe(Point::new(100, 500), Point::new(900, 500), 2, 300);
+        assert_eq!(points(&gesture.frames[0]), vec![(100, 460), (100, 540)]);
+    }
+
+    #[test]
+    fn sendevent_script_tracks_each_finger() {
+        let gesture = Gesture::pinch(Point::new(540, 1200), 200, 100, 200);
+        let script = sendevent_script(&device(), &gesture, (1080, 2400)).unwrap();
+        assert!(script.starts_with("sendevent /dev/input/event2 3 47 0; sendevent /dev/input/event2 3 57 1; "));
+        assert!(script.contains("sendevent /dev/input/event2 3 47 1; sendevent /dev/input/event2 3 57 2; "));
+        assert!(script.ends_with("sendevent /dev/input/event2 3 47 0; sendevent /dev/input/event2 3 57 -1; \
+                                  sendevent /dev/input/event2 3 47 1; sendevent /dev/input/event2 3 57 -1; \
+                                  sendevent /dev/input/event2 1 330 0; sendevent /dev/input/event2 0 0 0"));
+    }
+
+    #[test]
+    fn sendevent_script_rejects_more_fingers_than_slots() {
+        let device = TouchDevice { max_slots: Some(2), ..device() };
+        let gesture = Gesture::swipe(Point::new(500, 1500), Point::new(500, 500), 3, 300);
+        assert!(sendevent_script(&device, &gesture, (1080, 2400)).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// 按键码表 - Android KeyEvent 键码名称与数值的对照
+// 名称不区分大小写，可省略 KEYCODE_ 前缀；字母 A-Z 和数字 0-9 按规则换算，不在表中列出
+// 纯数字表示键码本身，数字键需写作 KEYCODE_0 ~ KEYCODE_9
+
+use crate::{Result, TkeError};
+
+// Android 14 中最大的键码 (KEYCODE_MACRO_4)，超出范围的数字键码视为无效
+pub const MAX_KEYCODE: u32 = 316;
+
+// 常用键码: (名称, 键码)
+pub const KEYCODES: [(&str, u32); 102] = [
+    ("SOFT_LEFT", 1),
+    ("SOFT_RIGHT", 2),
+    ("HOME", 3),
+    ("BACK", 4),
+    ("CALL", 5),
+    ("ENDCALL", 6),
+    ("STAR", 17),
+    ("POUND", 18),
+    ("DPAD_UP", 19),
+    ("DPAD_DOWN", 20),
+    ("DPAD_LEFT", 21),
+    ("DPAD_RIGHT", 22),
+    ("DPAD_CENTER", 23),
+    ("VOLUME_UP", 24),
+    ("VOLUME_DOWN", 25),
+    ("POWER", 26),
+    ("CAMERA", 27),
+    ("CLEAR", 28),
+    ("COMMA", 55),
+    ("PERIOD", 56),
+    ("ALT_LEFT", 57),
+    ("ALT_RIGHT", 58),
+    ("SHIFT_LEFT", 59),
+    ("SHIFT_RIGHT", 60),
+    ("TAB", 61),
+    ("SPACE", 62),
+    ("SYM", 63),
+    ("EXPLORER", 64),
+    ("ENVELOPE", 65),
+    ("ENTER", 66),
+    ("DEL", 67),
+    ("GRAVE", 68),
+    ("MINUS", 69),
+    ("EQUALS", 70),
+    ("LEFT_BRACKET", 71),
+    ("RIGHT_BRACKET", 72),
+    ("BACKSLASH", 73),
+    ("SEMICOLON", 74),
+    ("APOSTROPHE", 75),
+    ("SLASH", 76),
+    ("AT", 77),
+    ("HEADSETHOOK", 79),
+    ("FOCUS", 80),
+    ("PLUS", 81),
+    ("MENU", 82),
+    ("NOTIFICATION", 83),
+    ("SEARCH", 84),
+    ("MEDIA_PLAY_PAUSE", 85),
+    ("MEDIA_STOP", 86),
+    ("MEDIA_NEXT", 87),
+    ("MEDIA_PREVIOUS", 88),
+    ("MEDIA_REWIND", 89),
+    ("MEDIA_FAST_FORWARD", 90),
+    ("MUTE", 91),
+    ("PAGE_UP", 92),
+    ("PAGE_DOWN", 93),
+    ("ESCAPE", 111),
+    ("FORWARD_DEL", 112),
+    ("CTRL_LEFT", 113),
+    ("CTRL_RIGHT", 114),
+    ("CAPS_LOCK", 115),
+    ("META_LEFT", 117),
+    ("META_RIGHT", 118),
+    ("SYSRQ", 120),
+    ("MOVE_HOME", 122),
+    ("MOVE_END", 123),
+    ("INSERT", 124),
+    ("FORWARD", 125),
+    ("MEDIA_PLAY", 126),
+    ("MEDIA_PAUSE", 127),
+    ("F1", 131),
+    ("F2", 132),
+    ("F3", 133),
+    ("F4", 134),
+    ("F5", 135),
+    ("F6", 136),
+    ("F7", 137),
+    ("F8", 138),
+    ("F9", 139),
+    ("F10", 140),
+    ("F11", 141),
+    ("F12", 142),
+    ("VOLUME_MUTE", 164),
+    ("ZOOM_IN", 168),
+    ("ZOOM_OUT", 169),
+    ("SETTINGS", 176),
+    ("APP_SWITCH", 187),
+    ("CONTACTS", 207),
+    ("CALENDAR", 208),
+    ("MUSIC", 209),
+    ("CALCULATOR", 210),
+    ("ASSIST", 219),
+    ("BRIGHTNESS_DOWN", 220),
+    ("BRIGHTNESS_UP", 221),
+    ("SLEEP", 223),
+    ("WAKEUP", 224),
+    ("VOICE_ASSIST", 231),
+    ("CUT", 277),
+    ("COPY", 278),
+    ("PASTE", 279),
+    ("ALL_APPS", 284),
+    ("REFRESH", 285),
+];
+
+// 查找单个按键的键码: 名称（HOME / KEYCODE_HOME / home）或数字键码
+pub fn lookup(key: &str) -> Option<u32> {
+    let key = key.trim();
+    if let Ok(code) = key.parse::<u32>() {
+        return (1..=MAX_KEYCODE).contains(&code).then_some(code);
+    }
+
+    let name = key.to_ascii_uppercase();
+    let name = name.strip_prefix("KEYCODE_").unwrap_or(&name);
+
+    // 单个字母或数字: KEYCODE_A = 29, KEYCODE_0 = 7
+    if let [c] = name.as_bytes() {
+        return match c {
+            b'A'..=b'Z' => Some(29 + (c - b'A') as u32),
+            b'0'..=b'9' => Some(7 + (c - b'0') as u32),
+            _ => None,
+        };
+    }
+
+    KEYCODES.iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, code)| *code)
+}
+
+// 解析按键描述，组合键用 + 连接（如 CTRL_LEFT+A），返回各按键的键码
+pub fn parse(spec: &str) -> Result<Vec<u32>> {
+    let keys: Vec<&str> = spec.split('+').map(|k| k.trim()).collect();
+    if keys.iter().any(|k| k.is_empty()) {
+        return Err(TkeError::InvalidArgument(format!("按键不能为空: '{}'", spec)));
+    }
+
+    keys.iter()
+        .map(|key| lookup(key).ok_or_else(|| {
+            TkeError::InvalidArgument(format!("未知的按键 '{}'", key))
+        }))
+        .collect()
+}
+
+// 所有按键名称（用于拼写建议和编辑器补全）
+pub fn names() -> impl Iterator<Item = &'static str> {
+    KEYCODES.iter().map(|(name, _)| *name)
+}
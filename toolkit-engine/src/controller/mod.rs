@@ -1,12 +1,32 @@
 // Controller模块 - 负责ADB控制
 
-use crate::{Result, TkeError, DeviceInfo, AdbManager};
-use std::path::PathBuf;
-use std::process::Command;
+pub mod keycode;
+pub mod gesture;
+
+use crate::{Result, TkeError, DeviceInfo, PackageInfo, AdbManager, Point};
+use gesture::Gesture;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// 有截止时间时检查 adb 进程是否结束的间隔
+const DEADLINE_POLL_INTERVAL: Duration = Duration::from_millis(20);
 
 pub struct Controller {
     device_id: Option<String>,
     adb_manager: AdbManager,
+    // 截止时间（步骤的 超时= 修饰符）：到期时终止正在执行的 adb 命令，之后的命令直接失败
+    deadline: Option<Instant>,
+}
+
+// 设备 shell 命令的执行结果
+#[derive(Debug, Clone)]
+pub struct ShellOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
 }
 
 impl Controller {
@@ -20,6 +40,7 @@ impl Controller {
         Ok(Self {
             device_id,
             adb_manager,
+            deadline: None,
         })
     }
     
@@ -27,6 +48,15 @@ impl Controller {
     pub fn set_device(&mut self, device_id: Option<String>) {
         self.device_id = device_id;
     }
+
+    // 设置命令的截止时间（None 为不限时）
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
     
     // 获取连接的设备列表
     pub fn get_devices(&self) -> Result<Vec<String>> {
@@ -70,7 +100,7 @@ impl Controller {
     }
     
     // 获取截图
-    async fn capture_screenshot(&self, output_path: &PathBuf) -> Result<()> {
+    pub async fn capture_screenshot(&self, output_path: &PathBuf) -> Result<()> {
         let temp_path = "/sdcard/screenshot.png";
         
         // 在设备上截图
@@ -90,7 +120,7 @@ impl Controller {
     }
     
     // 获取UI树
-    async fn capture_ui_tree(&self, output_path: &PathBuf) -> Result<()> {
+    pub async fn capture_ui_tree(&self, output_path: &PathBuf) -> Result<()> {
         let temp_path = "/sdcard/ui_dump.xml";
         
         // 在设备上dump UI
@@ -154,6 +184,82 @@ impl Controller {
         Ok(())
     }
 
+    // 拖动: 先长按拾起元素，再移动到终点后松开
+    // - 优先使用 input draganddrop，按下后等待长按超时再移动
+    // - input 不支持 draganddrop 时（较早的系统版本或精简过 input 命令的 ROM）通过 sendevent 直接写触摸事件，
+    //   整个手势在一条 shell 命令中完成，需要 shell 用户对触摸屏设备有写权限
+    pub fn drag(&self, x1: i32, y1: i32, x2: i32, y2: i32, duration_ms: u32) -> Result<()> {
+        let output = self.adb_output(&[
+            "shell", "input", "draganddrop",
+            &x1.to_string(), &y1.to_string(),
+            &x2.to_string(), &y2.to_string(),
+            &duration_ms.to_string()
+        ])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        // 旧版本的 adb shell 不带回命令的退出码，只能根据输出判断 input 是否认识 draganddrop
+        // 其它失败（如设备离线）直接报错，不再尝试合成手势
+        if Self::is_unknown_input_command(&stdout) || Self::is_unknown_input_command(&stderr) {
+            return self.perform_gesture(&Gesture::drag(Point::new(x1, y1), Point::new(x2, y2), duration_ms));
+        }
+        if !output.status.success() || stdout.contains("Exception") || stderr.contains("Exception") {
+            return Err(TkeError::AdbError(format!("拖动失败: {}{}", stdout.trim(), stderr.trim())));
+        }
+
+        Ok(())
+    }
+
+    // input 不认识子命令时输出 "Unknown command: xxx" 或整段用法说明
+    fn is_unknown_input_command(output: &str) -> bool {
+        output.contains("Unknown command") || output.contains("Usage: input")
+    }
+
+    // 执行触摸手势: 找到触摸屏设备，把手势转换为 sendevent 序列后一次执行
+    pub fn perform_gesture(&self, gesture: &Gesture) -> Result<()> {
+        let devices = self.run_adb_command_output(&["shell", "getevent", "-p"])?;
+        let device = gesture::parse_touch_device(&devices)
+            .ok_or_else(|| TkeError::AdbError("未找到支持多点触控的触摸屏设备".to_string()))?;
+
+        let size_output = self.run_adb_command_output(&["shell", "wm", "size"])?;
+        let screen = self.parse_screen_size(&size_output);
+
+        let script = gesture::sendevent_script(&device, gesture, screen)?;
+        let output = self.adb_output(&["shell", &script])?;
+        // sendevent 没有写权限时只输出错误信息
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !stderr.trim().is_empty() {
+            return Err(TkeError::AdbError(format!("写入触摸事件失败 ({}): {}", device.path, stderr.trim())));
+        }
+
+        Ok(())
+    }
+
+    // 双指捏合（缩小）: 两指间距缩短 distance 像素
+    pub fn pinch_in(&self, x: i32, y: i32, distance: i32, duration_ms: u32) -> Result<()> {
+        let outer = gesture::MIN_FINGER_RADIUS + distance / 2;
+        self.perform_gesture(&Gesture::pinch(Point::new(x, y), outer, gesture::MIN_FINGER_RADIUS, duration_ms))
+    }
+
+    // 双指张开（放大）: 两指间距增加 distance 像素
+    pub fn pinch_out(&self, x: i32, y: i32, distance: i32, duration_ms: u32) -> Result<()> {
+        let outer = gesture::MIN_FINGER_RADIUS + distance / 2;
+        self.perform_gesture(&Gesture::pinch(Point::new(x, y), gesture::MIN_FINGER_RADIUS, outer, duration_ms))
+    }
+
+    // 双指旋转: 两指绕 (x, y) 转动 degrees 度（正数为顺时针）
+    pub fn rotate(&self, x: i32, y: i32, radius: i32, degrees: i32, duration_ms: u32) -> Result<()> {
+        self.perform_gesture(&Gesture::rotate(Point::new(x, y), radius, degrees, duration_ms))
+    }
+
+    // 多指滑动: fingers 个手指并排从起点滑动到终点
+    pub fn multi_finger_swipe(&self, x1: i32, y1: i32, x2: i32, y2: i32, fingers: usize, duration_ms: u32) -> Result<()> {
+        if !(2..=gesture::MAX_FINGERS).contains(&fingers) {
+            return Err(TkeError::InvalidArgument(format!("手指数必须在 2 到 {} 之间", gesture::MAX_FINGERS)));
+        }
+        self.perform_gesture(&Gesture::swipe(Point::new(x1, y1), Point::new(x2, y2), fingers, duration_ms))
+    }
+
     // 输入文本
     pub fn input_text(&self, text: &str) -> Result<()> {
         // 保存当前输入法
@@ -169,7 +275,7 @@ impl Controller {
         }
 
         // 等待输入法切换完成
-        std::thread::sleep(std::time::Duration::from_millis(300));
+        self.pause(Duration::from_millis(300));
 
         // 转义特殊字符
         let escaped = text
@@ -181,7 +287,7 @@ impl Controller {
 
         // 恢复原来的输入法
         if !current_ime.is_empty() && !current_ime.contains("not found") {
-            std::thread::sleep(std::time::Duration::from_millis(200));
+            self.pause(Duration::from_millis(200));
             let _ = self.run_adb_command(&["shell", "ime", "set", &current_ime]);
         }
 
@@ -265,6 +371,28 @@ impl Controller {
         Ok(())
     }
 
+    // 按键（名称或键码，组合键用 + 连接），返回发送的键码
+    // 长按使用 keyevent --longpress；组合键使用 keycombination（需要 Android 13 及以上）
+    pub fn press_key(&self, key: &str, long_press: bool) -> Result<Vec<u32>> {
+        let codes = keycode::parse(key)?;
+        let code_args: Vec<String> = codes.iter().map(|c| c.to_string()).collect();
+        let code_args: Vec<&str> = code_args.iter().map(|c| c.as_str()).collect();
+
+        let mut args = vec!["shell", "input"];
+        match (codes.len(), long_press) {
+            (1, false) => args.push("keyevent"),
+            (1, true) => args.extend(["keyevent", "--longpress"]),
+            (_, false) => args.push("keycombination"),
+            (_, true) => {
+                return Err(TkeError::InvalidArgument(format!("组合键不支持长按: '{}'", key)));
+            }
+        }
+        args.extend(code_args);
+
+        self.run_adb_command(&args)?;
+        Ok(codes)
+    }
+
     // 返回键
     pub fn back(&self) -> Result<()> {
         self.key_event("KEYCODE_BACK")
@@ -287,6 +415,152 @@ impl Controller {
         self.run_adb_command(&["shell", "am", "force-stop", package])?;
         Ok(())
     }
+
+    // 安装应用；replace 对应 -r（覆盖安装），grant_permissions 对应 -g（授予所有运行时权限）
+    pub fn install_app(&self, apk_path: &Path, replace: bool, grant_permissions: bool) -> Result<()> {
+        if !apk_path.is_file() {
+            return Err(TkeError::InvalidArgument(format!("APK文件不存在: {}", apk_path.display())));
+        }
+        let apk = apk_path.to_str()
+            .ok_or_else(|| TkeError::InvalidArgument("无效的APK路径".to_string()))?;
+
+        let mut args = vec!["install"];
+        if replace {
+            args.push("-r");
+        }
+        if grant_permissions {
+            args.push("-g");
+        }
+        args.push(apk);
+
+        let output = self.adb_output(&args)?;
+        Self::check_package_result("安装", &output)
+    }
+
+    // 卸载应用；keep_data 对应 -k（保留数据和缓存目录）
+    pub fn uninstall_app(&self, package: &str, keep_data: bool) -> Result<()> {
+        let args: &[&str] = if keep_data {
+            &["uninstall", "-k", package]
+        } else {
+            &["uninstall", package]
+        };
+        let output = self.adb_output(args)?;
+        Self::check_package_result("卸载", &output)
+    }
+
+    // 清除应用数据（pm clear）
+    pub fn clear_app_data(&self, package: &str) -> Result<()> {
+        let output = self.adb_output(&["shell", "pm", "clear", package])?;
+        Self::check_package_result("清除数据", &output)
+    }
+
+    // 授予运行时权限，权限名不含 . 时补全为 android.permission.xxx，返回完整的权限名
+    pub fn grant_permission(&self, package: &str, permission: &str) -> Result<String> {
+        self.change_permission("grant", package, permission)
+    }
+
+    // 撤销运行时权限，返回完整的权限名
+    pub fn revoke_permission(&self, package: &str, permission: &str) -> Result<String> {
+        self.change_permission("revoke", package, permission)
+    }
+
+    fn change_permission(&self, action: &str, package: &str, permission: &str) -> Result<String> {
+        let permission = if permission.contains('.') {
+            permission.to_string()
+        } else {
+            format!("android.permission.{}", permission)
+        };
+
+        let output = self.adb_output(&["shell", "pm", action, package, &permission])?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // 旧版本的 pm 出错时退出码仍为0，错误信息在输出中
+        if !output.status.success() || stderr.contains("Exception") || stderr.contains("Error") {
+            // 取最后一条异常信息（如 java.lang.IllegalArgumentException: Unknown permission）
+            let message = stderr.lines()
+                .rev()
+                .find(|line| line.contains("Exception") || line.contains("Error"))
+                .unwrap_or(stderr.trim());
+            let operation = if action == "grant" { "授予" } else { "撤销" };
+            return Err(TkeError::AdbError(format!("{}权限 {} 失败: {}", operation, permission, message.trim())));
+        }
+
+        Ok(permission)
+    }
+
+    // 列出已安装的应用及版本信息；third_party_only 只列出第三方应用，filter 为包名包含的文本
+    pub fn list_packages(&self, third_party_only: bool, filter: Option<&str>) -> Result<Vec<PackageInfo>> {
+        let mut args = vec!["shell", "pm", "list", "packages"];
+        if third_party_only {
+            args.push("-3");
+        }
+        if let Some(filter) = filter {
+            args.push(filter);
+        }
+        let list = self.run_adb_command_output(&args)?;
+        let packages: Vec<&str> = list.lines()
+            .filter_map(|line| line.trim().strip_prefix("package:"))
+            .collect();
+
+        // 版本信息来自 dumpsys package packages（一次获取所有应用）
+        let dump = self.run_adb_command_output(&["shell", "dumpsys", "package", "packages"])?;
+        let versions = Self::parse_package_versions(&dump);
+
+        let mut infos: Vec<PackageInfo> = packages.into_iter()
+            .map(|package| {
+                let (version_code, version_name) = versions.get(package).cloned().unwrap_or_default();
+                PackageInfo { package: package.to_string(), version_code, version_name }
+            })
+            .collect();
+        infos.sort_by(|a, b| a.package.cmp(&b.package));
+        Ok(infos)
+    }
+
+    // 解析 dumpsys package packages 输出中每个应用的 versionCode 和 versionName
+    fn parse_package_versions(dump: &str) -> HashMap<String, (Option<u64>, Option<String>)> {
+        let mut versions: HashMap<String, (Option<u64>, Option<String>)> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for line in dump.lines().map(|l| l.trim()) {
+            // Package [com.example.app] (1a2b3c):
+            if let Some(rest) = line.strip_prefix("Package [") {
+                current = rest.split(']').next().map(|p| p.to_string());
+                continue;
+            }
+            let Some(ref package) = current else {
+                continue;
+            };
+            let entry = versions.entry(package.clone()).or_default();
+
+            // versionCode=123 minSdk=21 targetSdk=33
+            if let Some(rest) = line.strip_prefix("versionCode=") {
+                if entry.0.is_none() {
+                    entry.0 = rest.split_whitespace().next().and_then(|v| v.parse().ok());
+                }
+            } else if let Some(name) = line.strip_prefix("versionName=") {
+                if entry.1.is_none() {
+                    entry.1 = Some(name.to_string());
+                }
+            }
+        }
+
+        versions
+    }
+
+    // 检查 install / uninstall / pm clear 的输出：成功时输出 Success，失败时为 Failure [错误码: 说明]
+    fn check_package_result(operation: &str, output: &std::process::Output) -> Result<()> {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() && stdout.lines().any(|line| line.trim() == "Success") {
+            return Ok(());
+        }
+
+        let text = format!("{}\n{}", stdout, stderr);
+        let reason = text.lines()
+            .find_map(|line| line.split_once("Failure [").map(|(_, rest)| rest.trim_end_matches(']')))
+            .or_else(|| text.lines().map(|l| l.trim()).find(|l| !l.is_empty()))
+            .unwrap_or("未知错误");
+        Err(TkeError::AdbError(format!("{}失败: {}", operation, reason.trim())))
+    }
     
     // 清理输入框
     pub fn clear_input(&self) -> Result<()> {
@@ -313,6 +587,21 @@ impl Controller {
         Ok(())
     }
     
+    // 执行设备 shell 命令；命令的退出码非0不视为错误，ADB 本身出错（如设备未连接）时返回错误
+    pub fn shell(&self, command: &str) -> Result<ShellOutput> {
+        let output = self.adb_output(&["shell", command])?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if stderr.starts_with("error:") {
+            return Err(TkeError::AdbError(format!("ADB命令执行失败: {}", stderr)));
+        }
+
+        Ok(ShellOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr,
+            exit_code: output.status.code().unwrap_or(-1),
+        })
+    }
+
     // 获取设备信息
     pub fn get_device_info(&self) -> Result<DeviceInfo> {
         let model = self.get_device_prop("ro.product.model")?;
@@ -355,17 +644,7 @@ impl Controller {
     
     // 执行ADB命令
     fn run_adb_command(&self, args: &[&str]) -> Result<()> {
-        let mut cmd = Command::new(self.adb_manager.adb_path());
-        
-        // 如果指定了设备ID，添加-s参数
-        if let Some(ref device_id) = self.device_id {
-            cmd.arg("-s").arg(device_id);
-        }
-        
-        cmd.args(args);
-        
-        let output = cmd.output()
-            .map_err(|e| TkeError::AdbError(format!("执行ADB命令失败: {}", e)))?;
+        let output = self.adb_output(args)?;
         
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -377,6 +656,18 @@ impl Controller {
     
     // 执行ADB命令并获取输出
     fn run_adb_command_output(&self, args: &[&str]) -> Result<String> {
+        let output = self.adb_output(args)?;
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(TkeError::AdbError(format!("ADB命令执行失败: {}", stderr)));
+        }
+        
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // 执行ADB命令，返回完整的输出和退出状态
+    fn adb_output(&self, args: &[&str]) -> Result<std::process::Output> {
         let mut cmd = Command::new(self.adb_manager.adb_path());
         
         // 如果指定了设备ID，添加-s参数
@@ -385,15 +676,60 @@ impl Controller {
         }
         
         cmd.args(args);
-        
-        let output = cmd.output()
+
+        let Some(deadline) = self.deadline else {
+            return cmd.output()
+                .map_err(|e| TkeError::AdbError(format!("执行ADB命令失败: {}", e)));
+        };
+        if Instant::now() >= deadline {
+            return Err(TkeError::AdbError(format!("已超过步骤的超时时间，未执行: adb {}", args.join(" "))));
+        }
+
+        let mut child = cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
             .map_err(|e| TkeError::AdbError(format!("执行ADB命令失败: {}", e)))?;
-        
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(TkeError::AdbError(format!("ADB命令执行失败: {}", stderr)));
+
+        // 在等待进程结束的同时读取输出，避免输出较多时管道写满导致进程阻塞
+        fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+            std::thread::spawn(move || {
+                let mut buffer = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buffer);
+                }
+                buffer
+            })
         }
-        
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    // 终止本地的 adb 进程，设备上对应的 shell 会话随之结束
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(TkeError::AdbError(format!("超过步骤的超时时间，已终止: adb {}", args.join(" "))));
+                }
+                Ok(None) => std::thread::sleep(DEADLINE_POLL_INTERVAL),
+                Err(e) => return Err(TkeError::AdbError(format!("等待ADB命令失败: {}", e))),
+            }
+        };
+
+        Ok(std::process::Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    // 暂停一段时间，有截止时间时最多暂停到截止时间
+    fn pause(&self, duration: Duration) {
+        let duration = match self.deadline {
+            Some(deadline) => duration.min(deadline.saturating_duration_since(Instant::now())),
+            None => duration,
+        };
+        std::thread::sleep(duration);
     }
-}
\ No newline at end of file
+}
@@ -16,6 +16,8 @@ pub struct Fetcher {
     // 屏幕尺寸
     screen_width: u32,
     screen_height: u32,
+    // 是否保留没有文本、不可交互的容器节点（相对定位需要完整的层级关系）
+    keep_containers: bool,
 }
 
 impl Fetcher {
@@ -47,6 +49,7 @@ impl Fetcher {
             filtered_resource_ids,
             screen_width: 1080,
             screen_height: 1920,
+            keep_containers: false,
         }
     }
     
@@ -54,6 +57,10 @@ impl Fetcher {
         self.screen_width = width;
         self.screen_height = height;
     }
+
+    pub fn set_keep_containers(&mut self, keep_containers: bool) {
+        self.keep_containers = keep_containers;
+    }
     
     // 从XML文件中提取所有UI元素
     pub fn fetch_elements_from_file(&self, xml_path: &PathBuf) -> Result<Vec<UIElement>> {
@@ -302,6 +309,11 @@ impl Fetcher {
         if !element.is_visible() {
             return false;
         }
+
+        // 保留容器节点时，所有可见元素都包含
+        if self.keep_containers {
+            return true;
+        }
         
         // JavaScript版本的宽松条件：任何有文本、可点击、可聚焦的元素都包含
         let clickable = element.clickable;
@@ -1,6 +1,8 @@
 // Controller 命令处理器
 
 use tke::{Result, Controller, JsonOutput};
+use tke::controller::{gesture, keycode};
+use std::path::PathBuf;
 
 /// Controller 命令枚举
 #[derive(clap::Subcommand)]
@@ -30,6 +32,11 @@ pub enum ControllerCommands {
         #[arg(short, long, default_value = "300")]
         duration: u32,
     },
+    /// 多点触控手势（双指缩放、旋转、多指滑动）
+    Gesture {
+        #[command(subcommand)]
+        action: GestureCommands,
+    },
     /// 启动应用
     Launch {
         /// 应用包名
@@ -42,6 +49,11 @@ pub enum ControllerCommands {
         /// 应用包名
         package: String,
     },
+    /// 应用管理（安装、卸载、清除数据、权限、已安装应用列表）
+    App {
+        #[command(subcommand)]
+        action: AppCommands,
+    },
     /// 输入文本
     Input {
         /// 要输入的文本
@@ -53,6 +65,136 @@ pub enum ControllerCommands {
     Back,
     /// 主页键
     Home,
+    /// 按键（键码名称如 HOME / KEYCODE_ENTER，或数字键码；组合键用 + 连接，如 CTRL_LEFT+A）
+    Key {
+        /// 按键名称或键码
+        #[arg(value_parser = parse_key)]
+        key: String,
+        /// 长按
+        #[arg(short, long)]
+        long: bool,
+    },
+}
+
+/// 多点触控手势命令枚举
+#[derive(clap::Subcommand)]
+pub enum GestureCommands {
+    /// 双指捏合（缩小）
+    PinchIn {
+        /// 中心X坐标
+        x: i32,
+        /// 中心Y坐标
+        y: i32,
+        /// 两指间距的变化(像素)
+        #[arg(short = 'D', long, default_value_t = gesture::DEFAULT_PINCH_DISTANCE)]
+        distance: i32,
+        /// 持续时间(毫秒)
+        #[arg(short, long, default_value = "500")]
+        duration: u32,
+    },
+    /// 双指张开（放大）
+    PinchOut {
+        /// 中心X坐标
+        x: i32,
+        /// 中心Y坐标
+        y: i32,
+        /// 两指间距的变化(像素)
+        #[arg(short = 'D', long, default_value_t = gesture::DEFAULT_PINCH_DISTANCE)]
+        distance: i32,
+        /// 持续时间(毫秒)
+        #[arg(short, long, default_value = "500")]
+        duration: u32,
+    },
+    /// 双指旋转
+    Rotate {
+        /// 中心X坐标
+        x: i32,
+        /// 中心Y坐标
+        y: i32,
+        /// 旋转角度（正数为顺时针，负数为逆时针）
+        #[arg(allow_negative_numbers = true)]
+        degrees: i32,
+        /// 手指到中心的距离(像素)
+        #[arg(short, long, default_value_t = gesture::DEFAULT_ROTATE_RADIUS)]
+        radius: i32,
+        /// 持续时间(毫秒)
+        #[arg(short, long, default_value = "500")]
+        duration: u32,
+    },
+    /// 多指滑动
+    Swipe {
+        /// 起点X坐标
+        x1: i32,
+        /// 起点Y坐标
+        y1: i32,
+        /// 终点X坐标
+        x2: i32,
+        /// 终点Y坐标
+        y2: i32,
+        /// 手指数(2-5)
+        #[arg(short, long, default_value = "2")]
+        fingers: usize,
+        /// 持续时间(毫秒)
+        #[arg(short, long, default_value = "300")]
+        duration: u32,
+    },
+}
+
+/// 应用管理命令枚举
+#[derive(clap::Subcommand)]
+pub enum AppCommands {
+    /// 安装APK
+    Install {
+        /// APK文件路径
+        apk: PathBuf,
+        /// 覆盖安装（保留数据）
+        #[arg(short = 'r', long)]
+        replace: bool,
+        /// 授予所有运行时权限
+        #[arg(short = 'g', long)]
+        grant_permissions: bool,
+    },
+    /// 卸载应用
+    Uninstall {
+        /// 应用包名
+        package: String,
+        /// 保留数据和缓存目录
+        #[arg(short = 'k', long)]
+        keep_data: bool,
+    },
+    /// 清除应用数据 (pm clear)
+    Clear {
+        /// 应用包名
+        package: String,
+    },
+    /// 授予运行时权限
+    Grant {
+        /// 应用包名
+        package: String,
+        /// 权限名（如 android.permission.CAMERA，可简写为 CAMERA）
+        permission: String,
+    },
+    /// 撤销运行时权限
+    Revoke {
+        /// 应用包名
+        package: String,
+        /// 权限名（如 android.permission.CAMERA，可简写为 CAMERA）
+        permission: String,
+    },
+    /// 列出已安装的应用及版本信息
+    List {
+        /// 包名包含的文本
+        filter: Option<String>,
+        /// 只列出第三方应用
+        #[arg(short = '3', long)]
+        third_party: bool,
+    },
+}
+
+/// 校验按键名称（对照内置键码表）
+fn parse_key(s: &str) -> std::result::Result<String, String> {
+    keycode::parse(s).map_err(|e| e.to_string())?;
+    Ok(s.trim().to_string())
 }
 
 /// 处理 Controller 相关命令
@@ -96,6 +238,9 @@ pub async fn handle(action: ControllerCommands, device_id: Option<String>, proje
                 "duration": duration
             }));
         }
+        ControllerCommands::Gesture { action } => {
+            handle_gesture(action, &controller)?;
+        }
         ControllerCommands::Launch { package, activity } => {
             controller.launch_app(&package, &activity)?;
             JsonOutput::print(serde_json::json!({
@@ -111,6 +256,9 @@ pub async fn handle(action: ControllerCommands, device_id: Option<String>, proje
                 "package": package
             }));
         }
+        ControllerCommands::App { action } => {
+            handle_app(action, &controller)?;
+        }
         ControllerCommands::Input { text } => {
             controller.input_text(&text)?;
             JsonOutput::print(serde_json::json!({
@@ -136,6 +284,121 @@ pub async fn handle(action: ControllerCommands, device_id: Option<String>, proje
                 "success": true
             }));
         }
+        ControllerCommands::Key { key, long } => {
+            let codes = controller.press_key(&key, long)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "key": key,
+                "keycodes": codes,
+                "long_press": long
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// 处理多点触控手势命令
+fn handle_gesture(action: GestureCommands, controller: &Controller) -> Result<()> {
+    match action {
+        GestureCommands::PinchIn { x, y, distance, duration } => {
+            controller.pinch_in(x, y, distance, duration)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "gesture": "pinch_in",
+                "center": {"x": x, "y": y},
+                "distance": distance,
+                "duration": duration
+            }));
+        }
+        GestureCommands::PinchOut { x, y, distance, duration } => {
+            controller.pinch_out(x, y, distance, duration)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "gesture": "pinch_out",
+                "center": {"x": x, "y": y},
+                "distance": distance,
+                "duration": duration
+            }));
+        }
+        GestureCommands::Rotate { x, y, degrees, radius, duration } => {
+            controller.rotate(x, y, radius, degrees, duration)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "gesture": "rotate",
+                "center": {"x": x, "y": y},
+                "degrees": degrees,
+                "radius": radius,
+                "duration": duration
+            }));
+        }
+        GestureCommands::Swipe { x1, y1, x2, y2, fingers, duration } => {
+            controller.multi_finger_swipe(x1, y1, x2, y2, fingers, duration)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "gesture": "swipe",
+                "from": {"x": x1, "y": y1},
+                "to": {"x": x2, "y": y2},
+                "fingers": fingers,
+                "duration": duration
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// 处理应用管理命令
+fn handle_app(action: AppCommands, controller: &Controller) -> Result<()> {
+    match action {
+        AppCommands::Install { apk, replace, grant_permissions } => {
+            controller.install_app(&apk, replace, grant_permissions)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "apk": apk.to_string_lossy(),
+                "replace": replace,
+                "grant_permissions": grant_permissions
+            }));
+        }
+        AppCommands::Uninstall { package, keep_data } => {
+            controller.uninstall_app(&package, keep_data)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "package": package,
+                "keep_data": keep_data
+            }));
+        }
+        AppCommands::Clear { package } => {
+            controller.clear_app_data(&package)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "package": package
+            }));
+        }
+        AppCommands::Grant { package, permission } => {
+            let permission = controller.grant_permission(&package, &permission)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "package": package,
+                "permission": permission
+            }));
+        }
+        AppCommands::Revoke { package, permission } => {
+            let permission = controller.revoke_permission(&package, &permission)?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "package": package,
+                "permission": permission
+            }));
+        }
+        AppCommands::List { filter, third_party } => {
+            let packages = controller.list_packages(third_party, filter.as_deref())?;
+            JsonOutput::print(serde_json::json!({
+                "success": true,
+                "total": packages.len(),
+                "packages": packages
+            }));
+        }
     }
 
     Ok(())
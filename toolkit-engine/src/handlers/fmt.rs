@@ -0,0 +1,93 @@
+// Fmt 命令处理器
+
+use tke::{Result, TkeError, JsonOutput, ScriptParser, Locale};
+use std::path::{Path, PathBuf};
+
+/// 处理 fmt 命令：格式化 .tks 脚本，check 模式下只检查不写入
+/// 指定 locale 时将关键字统一转换为该语言（zh / en）
+pub async fn handle(paths: Vec<PathBuf>, check: bool, locale: Option<String>, project_path: PathBuf) -> Result<()> {
+    let locale = match locale {
+        Some(name) => Some(name.parse::<Locale>()?),
+        None => None,
+    };
+
+    // 未指定路径时格式化整个项目
+    let targets = if paths.is_empty() { vec![project_path.clone()] } else { paths };
+
+    let mut scripts = Vec::new();
+    for target in &targets {
+        collect_scripts(target, &mut scripts)?;
+    }
+    scripts.sort();
+    scripts.dedup();
+
+    // 加载项目的关键字别名
+    let mut parser = ScriptParser::new();
+    parser.set_project_path(project_path);
+    let mut files = Vec::new();
+    let mut changed_files = 0;
+
+    for script in &scripts {
+        let content = std::fs::read_to_string(script)
+            .map_err(TkeError::IoError)?;
+        let formatted = parser.format(&content, locale);
+        let changed = formatted != content;
+
+        if changed {
+            changed_files += 1;
+            if !check {
+                std::fs::write(script, &formatted)
+                    .map_err(TkeError::IoError)?;
+            }
+        }
+
+        files.push(serde_json::json!({
+            "path": script.to_string_lossy(),
+            "changed": changed
+        }));
+    }
+
+    let output = serde_json::json!({
+        "success": !(check && changed_files > 0),
+        "check": check,
+        "total_files": scripts.len(),
+        "changed_files": changed_files,
+        "files": files
+    });
+
+    // check 模式下存在未格式化的文件时以退出码 1 退出（用于 CI）
+    if check && changed_files > 0 {
+        JsonOutput::error_raw(&output.to_string());
+    }
+
+    JsonOutput::print(output);
+    Ok(())
+}
+
+// 收集路径下的 .tks 文件（目录递归查找，跳过隐藏目录和 workarea）
+fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        scripts.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    if !path.is_dir() {
+        return Err(TkeError::InvalidArgument(format!("路径不存在: {}", path.display())));
+    }
+
+    for entry in std::fs::read_dir(path).map_err(TkeError::IoError)? {
+        let entry_path = entry.map_err(TkeError::IoError)?.path();
+        let name = entry_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+        if entry_path.is_dir() {
+            if name.starts_with('.') || name == "workarea" || name == "node_modules" {
+                continue;
+            }
+            collect_scripts(&entry_path, scripts)?;
+        } else if entry_path.extension().and_then(|e| e.to_str()) == Some("tks") {
+            scripts.push(entry_path);
+        }
+    }
+
+    Ok(())
+}
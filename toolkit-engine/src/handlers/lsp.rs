@@ -0,0 +1,11 @@
+// LSP 命令处理器
+
+use tke::{Result, LanguageServer};
+use std::path::PathBuf;
+
+/// 处理 LSP 命令：在 stdio 上运行 .tks 语言服务器
+pub async fn handle(project_path: PathBuf) -> Result<()> {
+    let mut server = LanguageServer::new(project_path);
+    // 语言服务器以阻塞方式读写 stdio
+    tokio::task::block_in_place(|| server.run())
+}
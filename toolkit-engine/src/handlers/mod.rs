@@ -7,6 +7,8 @@ pub mod runner;
 pub mod ocr;
 pub mod adb;
 pub mod aapt;
+pub mod lsp;
+pub mod fmt;
 
 // 重新导出命令枚举，方便 main.rs 使用
 pub use controller::ControllerCommands;
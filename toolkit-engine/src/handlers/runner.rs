@@ -1,6 +1,6 @@
 // Runner 命令处理器
 
-use tke::{Result, Runner, JsonOutput};
+use tke::{Result, Runner, JsonOutput, ExecutionResult};
 use std::path::PathBuf;
 
 /// Runner 命令枚举
@@ -18,33 +18,54 @@ pub enum RunCommands {
         /// 单行脚本指令内容（例如: "点击 [{100, 200}]"）
         line: String,
     },
+    /// 严格检查 .tks 脚本，输出诊断信息（返回JSON格式，不连接设备）
+    Check {
+        /// 脚本文件路径
+        script_path: PathBuf,
+    },
+    /// 静态检查项目中所有脚本的元素引用和语法（返回JSON格式，不连接设备）
+    Lint,
+}
+
+/// 解析 --var key=value 形式的脚本变量
+pub fn parse_variable(s: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = s.split_once('=')
+        .ok_or_else(|| format!("变量格式应为 key=value: {}", s))?;
+    if key.trim().is_empty() {
+        return Err(format!("变量名不能为空: {}", s));
+    }
+    Ok((key.trim().to_string(), value.to_string()))
 }
 
 /// 处理 Runner 相关命令
-pub async fn handle(action: RunCommands, project_path: PathBuf, device_id: Option<String>) -> Result<()> {
+pub async fn handle(
+    action: RunCommands,
+    project_path: PathBuf,
+    device_id: Option<String>,
+    vars: Vec<(String, String)>,
+    strict: bool,
+) -> Result<()> {
     let mut runner = Runner::new(project_path.clone(), device_id.clone());
+    runner.set_variables(vars.into_iter().collect());
+    runner.parser.set_strict(strict);
 
     match action {
         RunCommands::Script { script_path } => {
             // 执行单个脚本文件，返回 JSON 格式
             let result = runner.run_script_file(&script_path).await?;
 
-            // 输出 JSON 格式的执行结果
-            JsonOutput::print(serde_json::json!({
-                "success": result.success,
-                "case_id": result.case_id,
-                "script_name": result.script_name,
-                "start_time": result.start_time,
-                "end_time": result.end_time,
-                "error": result.error,
-                "steps": result.steps.iter().map(|step| serde_json::json!({
-                    "index": step.index,
-                    "command": step.command,
-                    "success": step.success,
-                    "error": step.error,
-                    "duration_ms": step.duration_ms
-                })).collect::<Vec<_>>()
-            }));
+            // 输出 JSON 格式的执行结果；数据驱动的脚本输出每行数据的结果
+            if result.rows.is_empty() {
+                JsonOutput::print(script_result_json(&result));
+            } else {
+                JsonOutput::print(serde_json::json!({
+                    "success": result.success,
+                    "total_rows": result.rows.len(),
+                    "successful_rows": result.rows.iter().filter(|r| r.success).count(),
+                    "failed_rows": result.rows.iter().filter(|r| !r.success).count(),
+                    "rows": result.rows.iter().map(row_result_json).collect::<Vec<_>>()
+                }));
+            }
         }
         RunCommands::Project => {
             // 执行项目中所有脚本，返回 JSON 格式
@@ -56,16 +77,27 @@ pub async fn handle(action: RunCommands, project_path: PathBuf, device_id: Optio
                 "total_scripts": results.len(),
                 "successful_scripts": results.iter().filter(|r| r.success).count(),
                 "failed_scripts": results.iter().filter(|r| !r.success).count(),
-                "scripts": results.iter().map(|result| serde_json::json!({
-                    "success": result.success,
-                    "case_id": result.case_id,
-                    "script_name": result.script_name,
-                    "start_time": result.start_time,
-                    "end_time": result.end_time,
-                    "error": result.error,
-                    "total_steps": result.steps.len(),
-                    "successful_steps": result.steps.iter().filter(|s| s.success).count(),
-                })).collect::<Vec<_>>()
+                "scripts": results.iter().map(|result| {
+                    let mut entry = serde_json::json!({
+                        "success": result.success,
+                        "case_id": result.case_id,
+                        "script_name": result.script_name,
+                        "tags": result.tags,
+                        "priority": result.priority,
+                        "start_time": result.start_time,
+                        "end_time": result.end_time,
+                        "error": result.error,
+                        "total_steps": result.steps.len(),
+                        "successful_steps": result.steps.iter().filter(|s| s.success).count(),
+                        "teardown_error": result.teardown_error,
+                    });
+                    // 数据驱动的脚本附带数据行的统计
+                    if !result.rows.is_empty() {
+                        entry["total_rows"] = result.rows.len().into();
+                        entry["successful_rows"] = result.rows.iter().filter(|r| r.success).count().into();
+                    }
+                    entry
+                }).collect::<Vec<_>>()
             }));
         }
         RunCommands::Step { line } => {
@@ -80,7 +112,14 @@ pub async fn handle(action: RunCommands, project_path: PathBuf, device_id: Optio
                         "success": step_result.success,
                         "command": line,
                         "duration_ms": step_result.duration_ms,
-                        "error": step_result.error
+                        "error": step_result.error,
+                        "attempts": step_result.attempts,
+                        "retry_errors": step_result.retry_errors,
+                        "screenshot": step_result.screenshot,
+                        "ui_xml": step_result.ui_xml,
+                        "variables": step_result.variables,
+                        "branch": step_result.branch,
+                        "children": step_result.children
                     }));
                 }
                 Err(e) => {
@@ -92,7 +131,72 @@ pub async fn handle(action: RunCommands, project_path: PathBuf, device_id: Optio
                 }
             }
         }
+        RunCommands::Check { script_path } => {
+            // 只解析检查，不执行
+            let diagnostics = runner.parser.check_file(&script_path)?;
+            let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+
+            let output = serde_json::json!({
+                "success": errors == 0,
+                "script": script_path.to_string_lossy(),
+                "errors": errors,
+                "warnings": diagnostics.len() - errors,
+                "diagnostics": diagnostics
+            });
+
+            // 存在错误时以退出码 1 退出（用于 CI）
+            if errors > 0 {
+                JsonOutput::error_raw(&output.to_string());
+            }
+            JsonOutput::print(output);
+        }
+        RunCommands::Lint => {
+            let report = runner.lint_project()?;
+            let errors = report.error_count();
+
+            let output = serde_json::json!({
+                "success": errors == 0,
+                "total_scripts": report.scripts.len(),
+                "errors": errors,
+                "warnings": report.warning_count(),
+                "scripts": report.scripts,
+                "unused_locators": report.unused_locators
+            });
+
+            // 存在错误时以退出码 1 退出（用于 CI）
+            if errors > 0 {
+                JsonOutput::error_raw(&output.to_string());
+            }
+            JsonOutput::print(output);
+        }
     }
 
     Ok(())
 }
+
+// 单个脚本执行结果的 JSON 输出
+fn script_result_json(result: &ExecutionResult) -> serde_json::Value {
+    serde_json::json!({
+        "success": result.success,
+        "case_id": result.case_id,
+        "script_name": result.script_name,
+        "details": result.details,
+        "tags": result.tags,
+        "priority": result.priority,
+        "start_time": result.start_time,
+        "end_time": result.end_time,
+        "error": result.error,
+        "setup": result.setup,
+        "steps": result.steps,
+        "teardown": result.teardown,
+        "teardown_error": result.teardown_error
+    })
+}
+
+// 数据驱动脚本中一行数据的 JSON 输出（附带行名称和绑定的变量）
+fn row_result_json(result: &ExecutionResult) -> serde_json::Value {
+    let mut json = script_result_json(result);
+    json["data_key"] = serde_json::json!(result.data_key);
+    json["data"] = serde_json::json!(result.data);
+    json
+}
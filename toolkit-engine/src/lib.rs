@@ -13,15 +13,17 @@ pub mod controller;
 pub mod fetcher;
 pub mod recognizer;
 pub mod runner;
+pub mod lsp;
 
 // 导出工具类
 pub use utils::{JsonOutput, AdbManager, AaptManager};
 
 // 导出功能模块
-pub use controller::Controller;
+pub use controller::{Controller, ShellOutput};
 pub use fetcher::Fetcher;
 pub use recognizer::Recognizer;
 pub use runner::{Runner, ScriptParser, ScriptInterpreter};
+pub use lsp::LanguageServer;
 
 // 导出 OCR 功能
 pub use ocr::{ocr, OcrResult, OcrText};
@@ -31,11 +33,19 @@ pub use models::{
     UIElement,
     Locator,
     LocatorType,
+    RelativeLocator,
+    Relation,
+    Anchor,
     TksScript,
+    TksData,
     TksStep,
+    StepModifiers,
     TksCommand,
     TksParam,
+    AttributeCondition,
+    Locale,
     DeviceInfo,
+    PackageInfo,
     Point,
     Bounds,
     ExecutionResult,
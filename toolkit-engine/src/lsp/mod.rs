@@ -0,0 +1,577 @@
+// LSP模块 - .tks 脚本的语言服务器（stdio, JSON-RPC）
+// 提供诊断、补全（命令/方向/元素名）、悬停（元素定义）和跳转到 element.json 定义
+
+use crate::controller::keycode;
+use crate::models::COMMAND_KEYWORDS;
+use crate::runner::{self, Diagnostic, Severity, LocatorReference, KeywordTable, BlockKeyword, Modifier};
+use crate::{Result, TkeError, Locator, LocatorType, Recognizer, ScriptParser, TksCommand, Locale};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+// LSP CompletionItemKind
+const KIND_FUNCTION: u32 = 3;
+const KIND_FIELD: u32 = 5;
+const KIND_KEYWORD: u32 = 14;
+const KIND_ENUM_MEMBER: u32 = 20;
+
+// XML元素查找策略
+const STRATEGIES: [&str; 5] = ["resourceId", "text", "className", "contentDesc", "xpath"];
+
+pub struct LanguageServer {
+    project_path: PathBuf,
+    parser: ScriptParser,
+    locators: HashMap<String, Locator>,
+    documents: HashMap<String, String>,  // uri -> 文档内容
+    locale: Option<Locale>,              // 补全和用法提示的关键字语言（None 时按文档内容判断）
+}
+
+impl LanguageServer {
+    pub fn new(project_path: PathBuf) -> Self {
+        let mut parser = ScriptParser::new();
+        parser.set_project_path(project_path.clone());
+
+        let mut server = Self {
+            project_path,
+            parser,
+            locators: HashMap::new(),
+            documents: HashMap::new(),
+            locale: None,
+        };
+        server.reload_locators();
+        server
+    }
+
+    // 在标准输入输出上处理消息，直到收到 exit 通知或输入结束
+    pub fn run(&mut self) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut reader = stdin.lock();
+        let stdout = std::io::stdout();
+        let mut writer = stdout.lock();
+
+        while let Some(message) = read_message(&mut reader)? {
+            let method = message["method"].as_str().unwrap_or_default().to_string();
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+            debug!("LSP 收到消息: {}", method);
+
+            if method == "exit" {
+                break;
+            }
+
+            match message.get("id") {
+                Some(id) => {
+                    let response = match self.handle_request(&method, &params) {
+                        Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        None => json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": -32601, "message": format!("不支持的方法: {}", method) }
+                        }),
+                    };
+                    write_message(&mut writer, &response)?;
+                }
+                None => {
+                    for notification in self.handle_notification(&method, &params) {
+                        write_message(&mut writer, &notification)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // 处理请求，返回 None 表示不支持该方法
+    fn handle_request(&mut self, method: &str, params: &Value) -> Option<Value> {
+        match method {
+            "initialize" => Some(self.initialize(params)),
+            "shutdown" => Some(Value::Null),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/hover" => Some(self.hover(params).unwrap_or(Value::Null)),
+            "textDocument/definition" => Some(self.definition(params).unwrap_or(Value::Null)),
+            _ => None,
+        }
+    }
+
+    // 处理通知，返回需要发送给客户端的通知
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                self.publish_diagnostics(&uri).into_iter().collect()
+            }
+            "textDocument/didChange" => {
+                // 全量同步：取最后一次变更的完整文本
+                let text = params["contentChanges"].as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                self.publish_diagnostics(&uri).into_iter().collect()
+            }
+            "textDocument/didSave" if uri.ends_with("element.json") => {
+                self.reload_locators();
+                self.publish_all_diagnostics()
+            }
+            "workspace/didChangeWatchedFiles" => {
+                self.reload_locators();
+                self.publish_all_diagnostics()
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] }
+                })]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // 初始化：优先使用客户端的工作区作为项目路径
+    // initializationOptions.locale 可指定补全使用的关键字语言（zh / en）
+    fn initialize(&mut self, params: &Value) -> Value {
+        self.locale = params["initializationOptions"]["locale"].as_str().and_then(|s| s.parse().ok());
+
+        let root = params["rootUri"].as_str()
+            .and_then(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        if let Some(root) = root.filter(|p| p.is_dir()) {
+            self.project_path = root.clone();
+            self.parser.set_project_path(root);
+            self.reload_locators();
+        }
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "completionProvider": { "triggerCharacters": ["{", "@", "&", "[", ","] },
+                "hoverProvider": true,
+                "definitionProvider": true
+            },
+            "serverInfo": { "name": "tke", "version": env!("BUILD_VERSION") }
+        })
+    }
+
+    // 重新加载 locator/element.json
+    fn reload_locators(&mut self) {
+        self.locators = match Recognizer::new(self.project_path.clone()) {
+            Ok(recognizer) => recognizer.locators().clone(),
+            Err(e) => {
+                warn!("加载 locator 定义失败: {}", e);
+                HashMap::new()
+            }
+        };
+    }
+
+    // === 诊断 ===
+
+    fn publish_all_diagnostics(&self) -> Vec<Value> {
+        self.documents.keys()
+            .filter_map(|uri| self.publish_diagnostics(uri))
+            .collect()
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Option<Value> {
+        let content = self.documents.get(uri)?;
+
+        let mut diagnostics = match uri_to_path(uri) {
+            Some(path) => self.parser.check_source(content, &path),
+            None => self.parser.check(content),
+        };
+        diagnostics.extend(runner::check_locator_references(
+            &self.project_path, &self.locators, &self.parser, content,
+        ));
+
+        let lines: Vec<&str> = content.lines().collect();
+        let diagnostics: Vec<Value> = diagnostics.iter()
+            .map(|d| to_lsp_diagnostic(d, &lines))
+            .collect();
+
+        Some(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics }
+        }))
+    }
+
+    // === 补全 ===
+
+    fn completion(&self, params: &Value) -> Value {
+        let items = self.position(params)
+            .map(|(content, line, column)| self.completion_items(content, line, column))
+            .unwrap_or_default();
+
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    fn completion_items(&self, content: &str, line_index: usize, column: usize) -> Vec<Value> {
+        let line = content.lines().nth(line_index).unwrap_or_default();
+        let prefix = &line[..byte_offset(line, column)];
+        let keywords = self.parser.keywords();
+        let locale = self.document_locale(content);
+        let in_steps = content.lines().take(line_index).any(|l| keywords.step_section(l.trim()).is_some());
+
+        // 步骤之前：头部关键字
+        if !in_steps {
+            return KeywordTable::section_keywords(locale).into_iter()
+                .map(|keyword| json!({ "label": format!("{}:", keyword), "kind": KIND_KEYWORD }))
+                .collect();
+        }
+
+        // {元素名 或 @{图片名：元素名
+        if let Some(open) = prefix.rfind('{').filter(|open| !prefix[*open..].contains('}')) {
+            let image = prefix[..open].ends_with('@');
+            let mut names: Vec<(&String, &Locator)> = self.locators.iter()
+                .filter(|(_, locator)| matches!(
+                    (&locator.locator_type, image),
+                    (LocatorType::Image, true) | (LocatorType::Xml, false)
+                ))
+                .collect();
+            names.sort_by_key(|(name, _)| name.as_str());
+            return names.into_iter()
+                .map(|(name, locator)| json!({
+                    "label": name,
+                    "kind": KIND_FIELD,
+                    "detail": locator_summary(locator)
+                }))
+                .collect();
+        }
+
+        // {元素名}&策略
+        if prefix.trim_end_matches(|c: char| c.is_ascii_alphabetic()).ends_with("}&") {
+            return STRATEGIES.iter()
+                .map(|strategy| json!({ "label": strategy, "kind": KIND_ENUM_MEMBER }))
+                .collect();
+        }
+
+        // 行首：命令和块关键字
+        let trimmed = prefix.trim_start();
+        if !trimmed.contains(|c: char| c.is_whitespace() || c == '[') {
+            // 当前语言的命令名，以及项目 keywords.json 中定义的别名
+            let aliases = self.parser.command_names().into_iter()
+                .filter(|name| TksCommand::from_str(name).is_none());
+            let mut items: Vec<Value> = COMMAND_KEYWORDS.iter()
+                .map(|(command, _, _)| command.keyword(locale))
+                .chain(aliases)
+                .filter_map(|name| self.parser.command(name).map(|command| (name, command)))
+                .map(|(name, command)| json!({
+                    "label": name,
+                    "kind": KIND_FUNCTION,
+                    "detail": runner::usage(command, locale)
+                }))
+                .collect();
+            items.extend([BlockKeyword::Else, BlockKeyword::End].into_iter()
+                .map(|block| json!({ "label": KeywordTable::block_keyword(block, locale), "kind": KIND_KEYWORD })));
+            return items;
+        }
+
+        // 方括号参数之后：步骤修饰符
+        if trimmed.rfind(']').is_some_and(|close| trimmed.find('[').is_some_and(|open| open < close)) {
+            return [Modifier::Retry, Modifier::Timeout, Modifier::AllowFailure].into_iter()
+                .map(|modifier| {
+                    let keyword = KeywordTable::modifier_keyword(modifier, locale);
+                    let label = if modifier.takes_value() { format!("{}=", keyword) } else { keyword.to_string() };
+                    json!({ "label": label, "kind": KIND_KEYWORD })
+                })
+                .collect();
+        }
+
+        // 按键的参数：按键名称和长按选项
+        let command_name = trimmed.split(|c: char| c.is_whitespace() || c == '[').next().unwrap_or_default();
+        if self.parser.command(command_name) == Some(&TksCommand::Key) {
+            return keycode::names()
+                .map(|name| json!({ "label": name, "kind": KIND_ENUM_MEMBER }))
+                .chain(std::iter::once(json!({ "label": KeywordTable::long_press_keyword(locale), "kind": KIND_KEYWORD })))
+                .collect();
+        }
+
+        // 等待的参数：元素状态和界面稳定
+        if self.parser.command(command_name) == Some(&TksCommand::Wait) {
+            return KeywordTable::state_keywords(locale).into_iter()
+                .map(|state| json!({ "label": state, "kind": KIND_ENUM_MEMBER }))
+                .chain(std::iter::once(json!({ "label": KeywordTable::ui_idle_keyword(locale), "kind": KIND_KEYWORD })))
+                .collect();
+        }
+
+        // 参数：方向、元素状态、属性条件和相对定位关系
+        KeywordTable::direction_keywords(locale).into_iter()
+            .chain(KeywordTable::state_keywords(locale))
+            .chain(KeywordTable::relation_keywords(locale))
+            .map(str::to_string)
+            .chain(KeywordTable::attribute_labels(locale))
+            .map(|value| json!({ "label": value, "kind": KIND_ENUM_MEMBER }))
+            .collect()
+    }
+
+    // 文档使用的关键字语言
+    fn document_locale(&self, content: &str) -> Locale {
+        self.locale.unwrap_or_else(|| self.parser.keywords().detect_locale(content))
+    }
+
+    // === 悬停 ===
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (content, line_index, column) = self.position(params)?;
+        let line = content.lines().nth(line_index)?;
+
+        if let Some(reference) = self.reference_at(content, line_index, column) {
+            let value = match self.locators.get(&reference.name) {
+                Some(locator) => locator_markdown(&reference.name, locator),
+                None => format!("未定义的元素 `{}`", reference.name),
+            };
+            return Some(json!({
+                "contents": { "kind": "markdown", "value": value },
+                "range": lsp_range(line, reference.line - 1, reference.start, reference.end)
+            }));
+        }
+
+        // 命令名：显示用法
+        let offset = line.chars().count() - line.trim_start().chars().count();
+        let name: String = line.trim_start()
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '[')
+            .collect();
+        let end = offset + name.chars().count();
+        if column < offset || column > end {
+            return None;
+        }
+
+        let command = self.parser.command(&name)?;
+        let signature = runner::signature(command);
+        let usage = runner::usage(command, self.document_locale(content));
+        let mut value = format!("```tks\n{}\n```", usage);
+        for (param, kind) in signature.params {
+            value.push_str(&format!("\n- {}: {}", param, kind.describe()));
+        }
+
+        Some(json!({
+            "contents": { "kind": "markdown", "value": value },
+            "range": lsp_range(line, line_index, offset, end)
+        }))
+    }
+
+    // === 跳转到定义 ===
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (content, line_index, column) = self.position(params)?;
+        let reference = self.reference_at(content, line_index, column)?;
+
+        let locator_file = self.project_path.join("locator").join("element.json");
+        let json_content = std::fs::read_to_string(&locator_file).ok()?;
+
+        // 查找 "元素名": 所在的位置
+        let key = serde_json::to_string(&reference.name).ok()?;
+        for (index, line) in json_content.lines().enumerate() {
+            let Some(pos) = line.find(&key) else {
+                continue;
+            };
+            if !line[pos + key.len()..].trim_start().starts_with(':') {
+                continue;
+            }
+            let start = line[..pos].chars().count();
+            let end = start + key.chars().count();
+            return Some(json!({
+                "uri": path_to_uri(&locator_file),
+                "range": lsp_range(line, index, start, end)
+            }));
+        }
+
+        None
+    }
+
+    // === 工具方法 ===
+
+    // 解析请求中的文档位置，返回 (文档内容, 行号, 字符列)
+    fn position<'a>(&'a self, params: &Value) -> Option<(&'a str, usize, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let content = self.documents.get(uri)?;
+        let line_index = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+
+        let line = content.lines().nth(line_index).unwrap_or_default();
+        Some((content.as_str(), line_index, char_column(line, character)))
+    }
+
+    // 查找光标所在的元素引用
+    fn reference_at(&self, content: &str, line_index: usize, column: usize) -> Option<LocatorReference> {
+        self.parser.locator_references(content)
+            .into_iter()
+            .find(|r| r.line == line_index + 1 && r.start <= column && column < r.end)
+    }
+}
+
+// 读取一条 LSP 消息（Content-Length 头 + JSON 内容），输入结束时返回 None
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = content_length.ok_or_else(|| {
+        TkeError::InvalidArgument("LSP 消息缺少 Content-Length".to_string())
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+// 写入一条 LSP 消息
+fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// 诊断转换为 LSP 格式（LSP 的列以 UTF-16 计）
+fn to_lsp_diagnostic(diagnostic: &Diagnostic, lines: &[&str]) -> Value {
+    let line_index = diagnostic.line.saturating_sub(1);
+    let line = lines.get(line_index).copied().unwrap_or_default();
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+
+    let mut value = json!({
+        "range": lsp_range(line, line_index, diagnostic.start, diagnostic.end),
+        "severity": severity,
+        "source": "tke",
+        "message": diagnostic.message
+    });
+    if let Some(ref suggestion) = diagnostic.suggestion {
+        value["data"] = json!({ "suggestion": suggestion });
+    }
+    value
+}
+
+// 将行内字符列范围转换为 LSP Range
+fn lsp_range(line: &str, line_index: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": line_index, "character": utf16_column(line, start) },
+        "end": { "line": line_index, "character": utf16_column(line, end) }
+    })
+}
+
+// 字符列 -> UTF-16 列
+fn utf16_column(line: &str, column: usize) -> usize {
+    line.chars().take(column).map(|c| c.len_utf16()).sum()
+}
+
+// UTF-16 列 -> 字符列
+fn char_column(line: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.chars().enumerate() {
+        if units >= utf16 {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.chars().count()
+}
+
+// 字符列 -> 字节位置
+fn byte_offset(line: &str, column: usize) -> usize {
+    line.char_indices().nth(column).map(|(pos, _)| pos).unwrap_or(line.len())
+}
+
+// 元素定义的简要说明（用于补全）
+fn locator_summary(locator: &Locator) -> String {
+    match locator.locator_type {
+        LocatorType::Image => locator.path.clone().unwrap_or_default(),
+        LocatorType::Xml => locator.resource_id.clone()
+            .or_else(|| locator.text.clone())
+            .or_else(|| locator.class_name.clone())
+            .unwrap_or_default(),
+    }
+}
+
+// 元素定义的详细说明（用于悬停）
+fn locator_markdown(name: &str, locator: &Locator) -> String {
+    let kind = match locator.locator_type {
+        LocatorType::Xml => "XML元素",
+        LocatorType::Image => "图片元素",
+    };
+    let mut value = format!("**{}** ({})", name, kind);
+
+    let fields = [
+        ("resource_id", locator.resource_id.clone()),
+        ("text", locator.text.clone()),
+        ("class_name", locator.class_name.clone()),
+        ("content_desc", locator.content_desc.clone()),
+        ("xpath", locator.xpath.clone()),
+        ("bounds", locator.bounds.as_ref().map(|b| format!("[{},{}][{},{}]", b.x1, b.y1, b.x2, b.y2))),
+        ("path", locator.path.clone()),
+        ("description", locator.description.clone()),
+    ];
+    for (field, field_value) in fields {
+        if let Some(field_value) = field_value {
+            value.push_str(&format!("\n- {}: `{}`", field, field_value));
+        }
+    }
+
+    value
+}
+
+// file:// URI -> 本地路径
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex: Vec<u8> = iter.by_ref().take(2).collect();
+            let decoded = std::str::from_utf8(&hex).ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())?;
+            bytes.push(decoded);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // Windows: file:///C:/... -> C:/...
+    if cfg!(windows) {
+        return Some(PathBuf::from(path.trim_start_matches('/')));
+    }
+    Some(PathBuf::from(path))
+}
+
+// 本地路径 -> file:// URI
+fn path_to_uri(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let text = path.to_string_lossy().replace('\\', "/");
+    let text = text.trim_start_matches("//?/");
+
+    let mut uri = String::from("file://");
+    if !text.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -51,6 +51,29 @@ enum Commands {
     Run {
         #[command(subcommand)]
         action: RunCommands,
+
+        /// Script variable as key=value (repeatable)
+        #[arg(long = "var", global = true, value_parser = runner::parse_variable)]
+        vars: Vec<(String, String)>,
+
+        /// Fail on any parse diagnostic error instead of skipping unrecognized lines
+        #[arg(long, global = true)]
+        strict: bool,
+    },
+    /// LSP - language server for .tks scripts over stdio
+    Lsp,
+    /// Fmt - format .tks scripts into canonical style
+    Fmt {
+        /// .tks files or directories (default: the whole project)
+        paths: Vec<PathBuf>,
+
+        /// Only check formatting, exit with 1 if any file would change
+        #[arg(long)]
+        check: bool,
+
+        /// Rewrite keywords in the given locale (zh or en); keeps them as written by default
+        #[arg(long)]
+        locale: Option<String>,
     },
     /// ADB - THIS IS JUST ADB!!!!! directly adb
     Adb {
@@ -100,7 +123,9 @@ async fn main() -> tke::Result<()> {
             Commands::Ocr { .. } |
             Commands::Controller { .. } |
             Commands::Recognizer { .. } |
-            Commands::Run { .. }
+            Commands::Run { .. } |
+            Commands::Lsp |
+            Commands::Fmt { .. }
         );
 
         // 初始化日志
@@ -163,8 +188,14 @@ async fn main() -> tke::Result<()> {
         Commands::Recognizer { action } => {
             recognizer::handle(action, project_path).await
         }
-        Commands::Run { action } => {
-            runner::handle(action, project_path, cli.device).await
+        Commands::Run { action, vars, strict } => {
+            runner::handle(action, project_path, cli.device, vars, strict).await
+        }
+        Commands::Lsp => {
+            lsp::handle(project_path).await
+        }
+        Commands::Fmt { paths, check, locale } => {
+            fmt::handle(paths, check, locale, project_path).await
         }
         Commands::Adb { args } => {
             adb::handle(args, cli.device).await
@@ -160,6 +160,34 @@ pub enum LocatorType {
     Image,
 }
 
+// 相对定位关系: 目标元素相对于锚点的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    RightOf,    // 右侧: 在锚点右边且与锚点在同一水平带内（优先）
+    LeftOf,     // 左侧
+    Above,      // 上方: 在锚点上边且与锚点在同一垂直带内（优先）
+    Below,      // 下方
+    Inside,     // 内部: 锚点的子孙元素
+    SameGroup,  // 同组: 与锚点的最近公共祖先层级最深（如 "张三" 所在行中的删除按钮）
+}
+
+// 相对定位的锚点: 定位器名称或元素文本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    Element(String),  // {元素名}，element.json 中的XML定位器
+    Text(String),     // "文本"，text/content-desc 包含该文本的元素
+}
+
+// 相对定位: 在所有匹配的元素中，选出与锚点满足关系的那一个
+// element.json 中写作 "relative": {"relation": "right_of", "anchor": {"element": "Wi-Fi标签"}}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelativeLocator {
+    pub relation: Relation,
+    pub anchor: Anchor,
+}
+
 // Locator定义 - 按照element.json格式
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Locator {
@@ -207,12 +235,29 @@ pub struct Locator {
     pub height: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub match_strategy: Option<String>,
+    // 相对定位（可选）: 匹配到多个元素时按与锚点的关系选择
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative: Option<RelativeLocator>,
     
     // 图像定位器字段
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
 }
 
+impl Locator {
+    // 获取脚本查找策略（{元素名}&策略）所依赖的字段值，未定义时返回 None
+    pub fn strategy_value(&self, strategy: &str) -> Option<&str> {
+        match strategy {
+            "resourceId" => self.resource_id.as_deref(),
+            "text" => self.text.as_deref(),
+            "className" => self.class_name.as_deref(),
+            "contentDesc" => self.content_desc.as_deref(),
+            "xpath" => self.xpath.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 
 // 设备信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -225,60 +270,202 @@ pub struct DeviceInfo {
     pub screen_height: u32,
 }
 
+// 已安装应用的信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    pub package: String,
+    pub version_code: Option<u64>,
+    pub version_name: Option<String>,
+}
+
+// 脚本关键字语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Zh,     // 中文关键字（点击 / 断言 / 步骤:）
+    En,     // 英文关键字（click / assert / steps:）
+}
+
+impl std::str::FromStr for Locale {
+    type Err = crate::TkeError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "zh" => Ok(Self::Zh),
+            "en" => Ok(Self::En),
+            _ => Err(crate::TkeError::InvalidArgument(format!("未知的语言 '{}'，可选: zh, en", s))),
+        }
+    }
+}
+
 // TKS脚本命令类型
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TksCommand {
     Launch,      // 启动
     Close,       // 关闭
+    Install,     // 安装
+    Uninstall,   // 卸载
+    ClearData,   // 清除数据
+    Grant,       // 授予权限
+    Revoke,      // 撤销权限
     Click,       // 点击
     Press,       // 按压
     Swipe,       // 滑动
     DirectionalSwipe,  // 定向滑动
+    Drag,        // 拖动
+    DirectionalDrag,   // 定向拖动
+    ScrollFind,  // 滚动查找（在可滚动容器中滑动直到目标可见）
+    PinchIn,     // 双指缩小（双指捏合）
+    PinchOut,    // 双指放大（双指张开）
+    Rotate,      // 双指旋转
+    MultiSwipe,  // 多指滑动
     Input,       // 输入
     Clear,       // 清理
     HideKeyboard, // 隐藏键盘
     Back,        // 返回
+    Key,         // 按键（键码名称或数字，支持长按和组合键）
+    Screenshot,  // 截图（保存到本次执行的结果目录）
+    Shell,       // 执行命令（设备 shell 命令，需在项目允许列表中）
     Wait,        // 等待
     Assert,      // 断言
+    Read,        // 读取
+    SetVariable, // 设置
+    If,          // 如果（块命令，以 否则/结束 分隔）
+    Repeat,      // 重复（块命令，固定次数）
+    RepeatUntil, // 重复直到（块命令，条件成立或达到最大次数时结束）
+    Call,        // 调用（执行另一个 .tks 脚本）
 }
 
+// 命令关键字表: (命令, 中文关键字, 英文关键字)
+pub const COMMAND_KEYWORDS: [(TksCommand, &str, &str); 33] = [
+    (TksCommand::Launch, "启动", "launch"),
+    (TksCommand::Close, "关闭", "close"),
+    (TksCommand::Install, "安装", "install"),
+    (TksCommand::Uninstall, "卸载", "uninstall"),
+    (TksCommand::ClearData, "清除数据", "clear_data"),
+    (TksCommand::Grant, "授予权限", "grant"),
+    (TksCommand::Revoke, "撤销权限", "revoke"),
+    (TksCommand::Click, "点击", "click"),
+    (TksCommand::Press, "按压", "press"),
+    (TksCommand::Swipe, "滑动", "swipe"),
+    (TksCommand::DirectionalSwipe, "定向滑动", "directional_swipe"),
+    (TksCommand::Drag, "拖动", "drag"),
+    (TksCommand::DirectionalDrag, "定向拖动", "directional_drag"),
+    (TksCommand::ScrollFind, "滚动查找", "scroll_find"),
+    (TksCommand::PinchIn, "双指缩小", "pinch_in"),
+    (TksCommand::PinchOut, "双指放大", "pinch_out"),
+    (TksCommand::Rotate, "双指旋转", "rotate"),
+    (TksCommand::MultiSwipe, "多指滑动", "multi_swipe"),
+    (TksCommand::Input, "输入", "input"),
+    (TksCommand::Clear, "清理", "clear"),
+    (TksCommand::HideKeyboard, "隐藏键盘", "hide_keyboard"),
+    (TksCommand::Back, "返回", "back"),
+    (TksCommand::Key, "按键", "key"),
+    (TksCommand::Screenshot, "截图", "screenshot"),
+    (TksCommand::Shell, "执行命令", "shell"),
+    (TksCommand::Wait, "等待", "wait"),
+    (TksCommand::Assert, "断言", "assert"),
+    (TksCommand::Read, "读取", "read"),
+    (TksCommand::SetVariable, "设置", "set"),
+    (TksCommand::If, "如果", "if"),
+    (TksCommand::Repeat, "重复", "repeat"),
+    (TksCommand::RepeatUntil, "重复直到", "repeat_until"),
+    (TksCommand::Call, "调用", "call"),
+];
+
 impl TksCommand {
+    // 根据中文或英文关键字查找命令
     pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "启动" => Some(Self::Launch),
-            "关闭" => Some(Self::Close),
-            "点击" => Some(Self::Click),
-            "按压" => Some(Self::Press),
-            "滑动" => Some(Self::Swipe),
-            "定向滑动" => Some(Self::DirectionalSwipe),
-            "输入" => Some(Self::Input),
-            "清理" => Some(Self::Clear),
-            "隐藏键盘" => Some(Self::HideKeyboard),
-            "返回" => Some(Self::Back),
-            "等待" => Some(Self::Wait),
-            "断言" => Some(Self::Assert),
-            _ => None,
+        COMMAND_KEYWORDS.iter()
+            .find(|(_, zh, en)| *zh == s || *en == s)
+            .map(|(command, _, _)| command.clone())
+    }
+
+    // 命令的脚本名称
+    pub fn to_str(&self) -> &'static str {
+        self.keyword(Locale::Zh)
+    }
+
+    // 命令在指定语言下的关键字
+    pub fn keyword(&self, locale: Locale) -> &'static str {
+        let (_, zh, en) = COMMAND_KEYWORDS.iter()
+            .find(|(command, _, _)| command == self)
+            .expect("COMMAND_KEYWORDS 包含所有命令");
+        match locale {
+            Locale::Zh => zh,
+            Locale::En => en,
         }
     }
+
+    // 是否为块命令（需要以 结束 闭合）
+    pub fn is_block(&self) -> bool {
+        matches!(self, Self::If | Self::Repeat | Self::RepeatUntil)
+    }
 }
 
 // TKS脚本参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TksParam {
-    Text(String),           // 纯文本
+    Text(String),           // 纯文本（可包含 ${变量名} 插值）
     Number(i32),            // 数字
     Duration(u32),          // 持续时间(毫秒)
     Coordinate(Point),      // 坐标 {x,y}
     /// XML元素
     /// - 基础格式: {元素名} - 全精确匹配
     /// - 指定策略: {元素名}&resourceId, {元素名}&text, {元素名}&className, {元素名}&xpath
+    /// - 相对定位: {元素名} 右侧 {锚点}, {元素名}&text 同组 "文本"
     XmlElement {
         name: String,
         strategy: Option<String>,  // 可选的查找策略：resourceId, text, className, xpath
+        relative: Option<RelativeLocator>,  // 可选的相对定位，优先于 locator 定义中的 relative
     },
     ImageElement(String),   // 图像元素 @{图片名}
     Direction(String),      // 方向 up/down/left/right
-    Boolean(bool),          // 布尔值
+    Boolean(bool),          // 布尔值（断言条件 存在/不存在）
+    Visibility(bool),       // 可见性（断言条件 可见/不可见）
+    Attribute(AttributeCondition),  // 元素属性条件（断言条件 已勾选/可用/文本包含=... 等）
+}
+
+// XML元素的属性条件
+// - 状态: 已勾选/未勾选、可用/不可用、已选中/未选中、已聚焦/未聚焦
+// - 文本: 文本等于=值、文本包含=值、文本匹配=正则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttributeCondition {
+    Checked(bool),
+    Enabled(bool),
+    Selected(bool),
+    Focused(bool),
+    TextEquals(String),
+    TextContains(String),
+    TextMatches(String),
+}
+
+impl AttributeCondition {
+    // 文本条件的值（状态条件为 None）
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Self::TextEquals(v) | Self::TextContains(v) | Self::TextMatches(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // 文本条件的值（可修改，用于变量插值）
+    pub fn value_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::TextEquals(v) | Self::TextContains(v) | Self::TextMatches(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // 替换文本条件的值（状态条件保持不变）
+    pub fn with_value(&self, value: impl Into<String>) -> Self {
+        let mut condition = self.clone();
+        if let Some(v) = condition.value_mut() {
+            *v = value.into();
+        }
+        condition
+    }
 }
 
 // TKS脚本步骤
@@ -286,37 +473,149 @@ pub enum TksParam {
 pub struct TksStep {
     pub command: TksCommand,
     pub params: Vec<TksParam>,
+    pub expected: Option<String>,  // 期望值（如 读取 [...] == 期望文本）
+    pub body: Vec<TksStep>,        // 块命令的子步骤（如果 的成立分支 / 循环体 / 调用的子脚本步骤）
+    pub else_body: Vec<TksStep>,   // 如果 的否则分支
+    pub callee: Option<PathBuf>,   // 调用 的子脚本路径（解析时确定）
+    pub modifiers: StepModifiers,  // 行尾的步骤修饰符（重试=3 超时=10s 允许失败）
     pub raw: String,
     pub line_number: usize,
 }
 
+// 步骤修饰符
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepModifiers {
+    pub retry: u32,                // 失败后的重试次数
+    pub timeout_ms: Option<u32>,   // 等待元素的最长时间，其他命令为步骤的最长执行时间
+    pub allow_failure: bool,       // 失败时继续执行后续步骤，且不影响用例结果
+}
+
 // TKS脚本
 #[derive(Debug, Clone)]
 pub struct TksScript {
     pub case_id: String,
     pub script_name: String,
     pub details: HashMap<String, String>,
+    pub tags: Vec<String>,
+    pub priority: Option<String>,
+    pub data: Option<TksData>,     // 数据: 每行数据执行一次脚本
+    pub setup: Vec<TksStep>,       // 前置: 步骤之前执行，失败时跳过步骤
     pub steps: Vec<TksStep>,
+    pub teardown: Vec<TksStep>,    // 后置: 无论前置和步骤是否成功都会执行
     pub file_path: Option<PathBuf>,
 }
 
+// 数据驱动的参数表（数据: 之后的内联表格，或 CSV/JSON 数据文件）
+#[derive(Debug, Clone, Default)]
+pub struct TksData {
+    pub source: Option<String>,    // 数据文件路径（相对用例文件夹），内联表格时为 None
+    pub columns: Vec<String>,      // 列名，执行时绑定为同名变量
+    pub rows: Vec<Vec<String>>,
+    pub line_number: usize,        // 数据: 所在的行号
+}
+
+impl TksData {
+    // 行的名称：名称/name 列的值，没有该列时使用第一列，为空时使用行号（从1开始）
+    pub fn row_key(&self, index: usize) -> String {
+        let column = self.columns.iter()
+            .position(|c| c == "名称" || c == "name")
+            .unwrap_or(0);
+
+        self.rows.get(index)
+            .and_then(|row| row.get(column))
+            .filter(|value| !value.is_empty())
+            .cloned()
+            .unwrap_or_else(|| (index + 1).to_string())
+    }
+
+    // 行数据绑定的变量（列名 -> 值）
+    pub fn row_variables(&self, index: usize) -> HashMap<String, String> {
+        let row = self.rows.get(index).map(|r| r.as_slice()).unwrap_or_default();
+        self.columns.iter()
+            .cloned()
+            .zip(row.iter().cloned())
+            .collect()
+    }
+}
+
 // 执行结果
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExecutionResult {
     pub success: bool,
     pub case_id: String,
     pub script_name: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub details: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// 数据驱动执行时当前数据行的名称（也会追加到 script_name 中）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_key: Option<String>,
+    /// 数据驱动执行时当前数据行绑定的变量
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub data: HashMap<String, String>,
     pub start_time: String,
     pub end_time: String,
+    /// 前置步骤的执行结果（失败时整个用例失败，步骤不再执行）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup: Vec<StepResult>,
     pub steps: Vec<StepResult>,
     pub error: Option<String>,
+    /// 后置步骤的执行结果（后置失败不影响 success）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teardown: Vec<StepResult>,
+    /// 后置步骤的第一个错误
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teardown_error: Option<String>,
+    /// 数据驱动执行时每行数据的执行结果（此时本结果为各行的汇总，不含步骤）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<ExecutionResult>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepResult {
     pub index: usize,
     pub command: String,
+    /// 步骤所在的行号
+    #[serde(default)]
+    pub line: usize,
+    /// 步骤所在的子脚本文件（仅 调用 的子步骤有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
     pub success: bool,
     pub error: Option<String>,
     pub duration_ms: u64,
+    /// 本步骤用到或设置的变量及其值
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+    /// 块命令实际执行的分支（如果 / 否则）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// 块命令内部子步骤的执行结果
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<StepResult>,
+    /// 所属循环的迭代序号（从0开始，仅循环体内的步骤有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
+    /// 执行次数（含重试）
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// 重试前每次失败的错误（最后一次的错误在 error 中）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retry_errors: Vec<String>,
+    /// 步骤失败但标记了 允许失败，不影响后续步骤和用例结果
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allowed_failure: bool,
+    /// 截图文件路径（截图 步骤的截图，或步骤失败时自动保存的现场截图）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+    /// 步骤失败时自动保存的UI树XML文件路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_xml: Option<String>,
+}
+
+fn default_attempts() -> u32 {
+    1
 }
\ No newline at end of file
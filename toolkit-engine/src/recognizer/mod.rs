@@ -4,7 +4,7 @@ mod xml;
 mod image;
 mod text;
 
-use crate::{Result, Locator, Point};
+use crate::{Result, Locator, Point, Bounds, UIElement, RelativeLocator};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -49,6 +49,11 @@ impl Recognizer {
         Ok(())
     }
 
+    // 获取已加载的locator定义
+    pub fn locators(&self) -> &HashMap<String, Locator> {
+        &self.locators
+    }
+
     // 设置置信度阈值
     pub fn set_confidence_threshold(&mut self, threshold: f32) {
         self.confidence_threshold = threshold;
@@ -61,8 +66,29 @@ impl Recognizer {
     /// # 参数
     /// - `locator_name`: 元素名称（locator/element.json 中定义的 key）
     /// - `strategy`: 可选的查找策略覆盖（从脚本语法 {元素名}&策略 中提取）
-    pub fn find_xml_element(&self, locator_name: &str, strategy: Option<&str>) -> Result<Point> {
-        xml::find_by_locator(&self.project_path, &self.locators, locator_name, strategy)
+    /// - `relative`: 可选的相对定位（从脚本语法 {元素名} 关系 {锚点} 中提取）
+    pub fn find_xml_element(&self, locator_name: &str, strategy: Option<&str>, relative: Option<&RelativeLocator>) -> Result<Point> {
+        xml::find_by_locator(&self.project_path, &self.locators, locator_name, strategy, relative)
+    }
+
+    /// 根据XML locator查找元素，返回匹配到的完整UI元素
+    pub fn find_xml_ui_element(&self, locator_name: &str, strategy: Option<&str>, relative: Option<&RelativeLocator>) -> Result<UIElement> {
+        xml::find_element(&self.project_path, &self.locators, locator_name, strategy, relative)
+    }
+
+    /// 判断XML locator对应的元素是否可见（在屏幕内且未被完全遮挡）
+    pub fn is_xml_element_visible(&self, locator_name: &str, strategy: Option<&str>, relative: Option<&RelativeLocator>) -> Result<bool> {
+        xml::is_visible(&self.project_path, &self.locators, locator_name, strategy, relative)
+    }
+
+    /// 当前界面中面积最大的可滚动元素
+    pub fn find_scrollable_container(&self) -> Result<UIElement> {
+        xml::find_largest_scrollable(&self.project_path)
+    }
+
+    /// 当前界面中完全位于区域内的元素
+    pub fn elements_within(&self, area: &Bounds) -> Result<Vec<UIElement>> {
+        xml::elements_within(&self.project_path, area)
     }
 
     // === 图像匹配查找 ===
@@ -83,4 +109,9 @@ impl Recognizer {
     pub fn find_element_by_text(&self, text: &str) -> Result<Point> {
         text::find_by_text(&self.project_path, text)
     }
+
+    /// 直接根据文本查找元素，返回匹配到的完整UI元素
+    pub fn find_ui_element_by_text(&self, text: &str) -> Result<UIElement> {
+        text::find_element(&self.project_path, text)
+    }
 }
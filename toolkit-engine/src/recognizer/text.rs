@@ -1,10 +1,16 @@
 // 文本查找模块 - 根据文本内容查找元素
 
-use crate::{Result, TkeError, Point, Fetcher};
+use crate::{Result, TkeError, Point, Fetcher, UIElement};
 use std::path::PathBuf;
 
 /// 根据文本查找元素
 pub fn find_by_text(project_path: &PathBuf, text: &str) -> Result<Point> {
+    let element = find_element(project_path, text)?;
+    Ok(element.center())
+}
+
+/// 根据文本查找元素，返回完整的UI元素（包含 bounds、text 和状态属性）
+pub fn find_element(project_path: &PathBuf, text: &str) -> Result<UIElement> {
     // 获取当前UI树
     let ui_tree_path = project_path.join("workarea").join("current_ui_tree.xml");
     let xml_content = std::fs::read_to_string(&ui_tree_path)
@@ -15,9 +21,7 @@ pub fn find_by_text(project_path: &PathBuf, text: &str) -> Result<Point> {
     let elements = fetcher.fetch_elements_from_xml(&xml_content)?;
 
     // 查找匹配文本的元素
-    let element = elements.iter()
+    elements.into_iter()
         .find(|e| e.matches_text(text))
-        .ok_or_else(|| TkeError::ElementNotFound(format!("未找到包含文本 '{}' 的元素", text)))?;
-
-    Ok(element.center())
+        .ok_or_else(|| TkeError::ElementNotFound(format!("未找到包含文本 '{}' 的元素", text)))
 }
@@ -1,37 +1,185 @@
 // XML元素查找模块 - 根据Locator定义查找UI元素
 
-use crate::{Result, TkeError, Point, UIElement, Locator, Fetcher};
+use crate::{Result, TkeError, Point, Bounds, UIElement, Locator, Fetcher, RelativeLocator, Relation, Anchor};
 use std::path::PathBuf;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tracing::debug;
 
+// 锚点本身也可以是相对定位的元素，限制嵌套层数避免循环引用
+const MAX_RELATIVE_DEPTH: usize = 4;
+
 /// 根据XML locator查找元素
 ///
 /// # 参数
 /// - `strategy_override`: 脚本中指定的策略（如 {元素名}&resourceId），优先于 locator 定义中的 matchStrategy
+/// - `relative`: 脚本中指定的相对定位（如 {开关} 右侧 {标签}），优先于 locator 定义中的 relative
 pub fn find_by_locator(
     project_path: &PathBuf,
     locators: &HashMap<String, Locator>,
     locator_name: &str,
-    strategy_override: Option<&str>  // 脚本语法指定的策略
+    strategy_override: Option<&str>,  // 脚本语法指定的策略
+    relative: Option<&RelativeLocator>
 ) -> Result<Point> {
-    // 获取当前UI树
+    let element = find_element(project_path, locators, locator_name, strategy_override, relative)?;
+    Ok(element.center())
+}
+
+/// 根据XML locator查找元素，返回完整的UI元素（包含 bounds、text 等属性）
+pub fn find_element(
+    project_path: &PathBuf,
+    locators: &HashMap<String, Locator>,
+    locator_name: &str,
+    strategy_override: Option<&str>,
+    relative: Option<&RelativeLocator>
+) -> Result<UIElement> {
+    // 获取locator定义
+    let locator = locators.get(locator_name)
+        .ok_or_else(|| TkeError::ElementNotFound(format!("Locator '{}' 未定义", locator_name)))?;
+    let (elements, _) = load_current_elements(project_path, has_relative(locator, relative))?;
+
+    // 查找匹配的元素
+    find_element_by_locator(&elements, locators, locator, strategy_override, relative, 0)
+}
+
+/// 判断XML locator对应的元素是否可见
+///
+/// 可见需同时满足:
+/// 1. 元素存在且尺寸不为0
+/// 2. 元素与屏幕范围有交集
+/// 3. 元素的屏幕内区域没有被绘制在它上层的元素完全遮挡
+///
+/// UI树按绘制顺序排列，排在目标之后、且不是其子孙的元素绘制在目标之上。
+/// 弹窗、遮罩这类遮挡者通常是没有文本、不可点击的布局容器，所以这里保留容器节点。
+pub fn is_visible(
+    project_path: &PathBuf,
+    locators: &HashMap<String, Locator>,
+    locator_name: &str,
+    strategy_override: Option<&str>,
+    relative: Option<&RelativeLocator>
+) -> Result<bool> {
+    let locator = locators.get(locator_name)
+        .ok_or_else(|| TkeError::ElementNotFound(format!("Locator '{}' 未定义", locator_name)))?;
+    let (elements, screen) = load_current_elements(project_path, true)?;
+
+    let target = match find_element_by_locator(&elements, locators, locator, strategy_override, relative, 0) {
+        Ok(element) => element,
+        Err(_) => return Ok(false),
+    };
+
+    if !target.is_visible() {
+        debug!("元素 '{}' 尺寸为0，不可见", locator_name);
+        return Ok(false);
+    }
+
+    // 屏幕内的可见区域
+    let visible_area = match screen.as_ref().and_then(|s| intersect(&target.bounds, s)) {
+        Some(area) => area,
+        None if screen.is_none() => target.bounds.clone(),
+        None => {
+            debug!("元素 '{}' 在屏幕范围之外，不可见", locator_name);
+            return Ok(false);
+        }
+    };
+
+    let occluders: Vec<&UIElement> = elements.iter()
+        .filter(|e| e.index > target.index)
+        .filter(|e| !is_ancestor_of(&elements, target.index, e.index))
+        .filter(|e| intersect(&e.bounds, &visible_area).is_some())
+        .collect();
+
+    if is_fully_covered(&visible_area, &occluders) {
+        debug!("元素 '{}' 被上层的元素完全遮挡，不可见", locator_name);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// 当前UI树中面积最大的可滚动元素（滚动查找的默认容器）
+pub fn find_largest_scrollable(project_path: &PathBuf) -> Result<UIElement> {
+    let (elements, _) = load_current_elements(project_path, false)?;
+
+    elements.into_iter()
+        .filter(|e| e.scrollable && e.is_visible())
+        .max_by_key(|e| e.bounds.width() as i64 * e.bounds.height() as i64)
+        .ok_or_else(|| TkeError::ElementNotFound("当前界面没有可滚动的元素".to_string()))
+}
+
+/// 当前UI树中完全位于区域内的元素
+pub fn elements_within(project_path: &PathBuf, area: &Bounds) -> Result<Vec<UIElement>> {
+    let (elements, _) = load_current_elements(project_path, false)?;
+
+    Ok(elements.into_iter()
+        .filter(|e| {
+            e.bounds.x1 >= area.x1 && e.bounds.y1 >= area.y1 &&
+            e.bounds.x2 <= area.x2 && e.bounds.y2 <= area.y2
+        })
+        .collect())
+}
+
+/// 读取当前UI树，返回所有UI元素以及屏幕范围（根据UI树推断）
+/// - `keep_containers`: 保留布局容器节点，用于相对定位中的 内部/同组 关系和以容器为锚点的情况
+fn load_current_elements(project_path: &PathBuf, keep_containers: bool) -> Result<(Vec<UIElement>, Option<Bounds>)> {
     let ui_tree_path = project_path.join("workarea").join("current_ui_tree.xml");
     let xml_content = std::fs::read_to_string(&ui_tree_path)
         .map_err(|e| TkeError::IoError(e))?;
 
     // 提取所有UI元素
-    let fetcher = Fetcher::new();
+    let mut fetcher = Fetcher::new();
+    fetcher.set_keep_containers(keep_containers);
     let elements = fetcher.fetch_elements_from_xml(&xml_content)?;
+    let screen = fetcher.infer_screen_size_from_xml(&xml_content)?
+        .map(|(width, height)| Bounds::new(0, 0, width as i32, height as i32));
 
-    // 获取locator定义
-    let locator = locators.get(locator_name)
-        .ok_or_else(|| TkeError::ElementNotFound(format!("Locator '{}' 未定义", locator_name)))?;
+    Ok((elements, screen))
+}
 
-    // 查找匹配的元素
-    let element = find_element_by_locator(&elements, locator, strategy_override)?;
+/// 脚本或 locator 定义中是否指定了相对定位
+fn has_relative(locator: &Locator, relative: Option<&RelativeLocator>) -> bool {
+    relative.is_some() || locator.relative.is_some()
+}
 
-    Ok(element.center())
+/// 计算两个矩形的交集
+fn intersect(a: &Bounds, b: &Bounds) -> Option<Bounds> {
+    let area = Bounds::new(a.x1.max(b.x1), a.y1.max(b.y1), a.x2.min(b.x2), a.y2.min(b.y2));
+    if area.is_visible() { Some(area) } else { None }
+}
+
+/// 判断 ancestor 是否为 index 对应元素的祖先
+fn is_ancestor_of(elements: &[UIElement], ancestor: usize, index: usize) -> bool {
+    let mut current = elements.get(index).and_then(|e| e.parent_index);
+    while let Some(parent) = current {
+        if parent == ancestor {
+            return true;
+        }
+        current = elements.get(parent).and_then(|e| e.parent_index);
+    }
+    false
+}
+
+/// 采样判断区域是否被遮挡元素完全覆盖
+fn is_fully_covered(area: &Bounds, occluders: &[&UIElement]) -> bool {
+    const SAMPLES: i32 = 5;
+
+    if occluders.is_empty() {
+        return false;
+    }
+
+    for i in 0..SAMPLES {
+        for j in 0..SAMPLES {
+            // 取每个采样格的中心点
+            let x = area.x1 + area.width() * (2 * i + 1) / (2 * SAMPLES);
+            let y = area.y1 + area.height() * (2 * j + 1) / (2 * SAMPLES);
+            let covered = occluders.iter().any(|e| {
+                x >= e.bounds.x1 && x < e.bounds.x2 && y >= e.bounds.y1 && y < e.bounds.y2
+            });
+            if !covered {
+                return false;
+            }
+        }
+    }
+
+    true
 }
 
 /// 根据locator定义查找元素
@@ -40,36 +188,156 @@ pub fn find_by_locator(
 /// 1. 如果脚本指定了策略（如 {元素名}&resourceId），则**只使用该策略，严格匹配**
 /// 2. 如果脚本没有指定策略（仅 {元素名}），则使用**全精确匹配**（所有 locator 字段都必须匹配）
 /// 3. 移除了原有的瀑布式匹配逻辑，避免找错元素
+/// 4. 有相对定位时，在所有匹配的元素中选出与锚点满足关系的元素，否则使用第一个
 fn find_element_by_locator(
     elements: &[UIElement],
+    locators: &HashMap<String, Locator>,
     locator: &Locator,
-    strategy_override: Option<&str>
+    strategy_override: Option<&str>,
+    relative: Option<&RelativeLocator>,
+    depth: usize
 ) -> Result<UIElement> {
     debug!("🔍 开始查找元素");
     debug!("  - Locator 定义: {:?}", locator);
     debug!("  - 脚本指定策略: {:?}", strategy_override);
     debug!("  - 当前有 {} 个UI元素可供匹配", elements.len());
 
-    // 🔥 情况1: 脚本指定了策略（如 {登录按钮}#resourceId），只使用该策略
-    if let Some(strategy) = strategy_override {
+    let matches = if let Some(strategy) = strategy_override {
+        // 🔥 情况1: 脚本指定了策略（如 {登录按钮}#resourceId），只使用该策略
         debug!("✅ 使用脚本指定的策略: {}", strategy);
-        return find_by_single_strategy(elements, locator, strategy);
+        find_by_single_strategy(elements, locator, strategy)?
+    } else {
+        // 🔥 情况2: 脚本没有指定策略（仅 {登录按钮}），使用全精确匹配
+        debug!("✅ 使用全精确匹配模式（所有字段必须匹配）");
+        find_by_exact_match_strict(elements, locator)?
+    };
+
+    // 🔥 情况3: 相对定位（脚本中指定的优先于 locator 定义中的）
+    if let Some(relative) = relative.or(locator.relative.as_ref()) {
+        let anchor = find_anchor(elements, locators, &relative.anchor, depth)?;
+        debug!("✅ 使用相对定位: {:?}，锚点: text={:?}, bounds={:?}", relative.relation, anchor.text, anchor.bounds);
+        return pick_relative(elements, &matches, relative.relation, &anchor)
+            .cloned()
+            .ok_or_else(|| TkeError::ElementNotFound(format!(
+                "{} 个匹配元素中没有位于锚点{}的元素", matches.len(), relation_name(relative.relation)
+            )));
     }
 
-    // 🔥 情况2: 脚本没有指定策略（仅 {登录按钮}），使用全精确匹配
-    debug!("✅ 使用全精确匹配模式（所有字段必须匹配）");
-    find_by_exact_match_strict(elements, locator)
+    // 🔥 如果找到多个匹配，警告并返回第一个
+    if matches.len() > 1 {
+        debug!("⚠️ 警告: 找到 {} 个匹配元素，将使用第一个", matches.len());
+        for (idx, element) in matches.iter().enumerate() {
+            debug!("  [{}/{}] text={:?}, resource_id={:?}, class={}, bounds={:?}",
+                   idx + 1, matches.len(),
+                   element.text, element.resource_id, element.class_name, element.bounds);
+        }
+    } else {
+        debug!("✅ 找到唯一匹配元素: text={:?}, resource_id={:?}",
+               matches[0].text, matches[0].resource_id);
+    }
+
+    Ok(matches[0].clone())
 }
 
-/// 🔥 使用单一策略查找（严格匹配，不使用 contains）
-fn find_by_single_strategy(
+// ========== 相对定位 ==========
+
+/// 查找相对定位的锚点元素
+fn find_anchor(
     elements: &[UIElement],
+    locators: &HashMap<String, Locator>,
+    anchor: &Anchor,
+    depth: usize
+) -> Result<UIElement> {
+    match anchor {
+        Anchor::Element(name) => {
+            if depth >= MAX_RELATIVE_DEPTH {
+                return Err(TkeError::InvalidArgument(format!("锚点 '{}' 的相对定位嵌套过深（可能存在循环引用）", name)));
+            }
+            let locator = locators.get(name)
+                .ok_or_else(|| TkeError::ElementNotFound(format!("锚点 Locator '{}' 未定义", name)))?;
+            find_element_by_locator(elements, locators, locator, None, None, depth + 1)
+                .map_err(|e| TkeError::ElementNotFound(format!("未找到锚点 '{}': {}", name, e)))
+        }
+        Anchor::Text(text) => elements.iter()
+            .find(|e| e.matches_text(text))
+            .cloned()
+            .ok_or_else(|| TkeError::ElementNotFound(format!("未找到包含文本 '{}' 的锚点元素", text))),
+    }
+}
+
+/// 在候选元素中选出与锚点满足关系的元素（锚点本身不参与选择）
+/// - 右侧/左侧/上方/下方: 元素中心越过锚点对应的边；优先与锚点在同一行/列，再按间距和错位距离取最近的
+/// - 内部: 锚点的子孙元素中的第一个
+/// - 同组: 与锚点的最近公共祖先层级最深的元素（同一行/卡片中的元素），相同时取第一个
+fn pick_relative<'a>(
+    elements: &[UIElement],
+    candidates: &[&'a UIElement],
+    relation: Relation,
+    anchor: &UIElement
+) -> Option<&'a UIElement> {
+    let mut candidates = candidates.iter().copied().filter(|c| c.index != anchor.index);
+
+    match relation {
+        Relation::RightOf | Relation::LeftOf | Relation::Above | Relation::Below => candidates
+            .filter_map(|c| directional_distance(&anchor.bounds, &c.bounds, relation).map(|distance| (distance, c)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, c)| c),
+        Relation::Inside => candidates.find(|c| is_ancestor_of(elements, anchor.index, c.index)),
+        Relation::SameGroup => candidates
+            .filter_map(|c| common_ancestor_depth(elements, anchor.index, c.index).map(|depth| (depth, c)))
+            .min_by_key(|(depth, _)| std::cmp::Reverse(*depth))
+            .map(|(_, c)| c),
+    }
+}
+
+/// 目标相对锚点在指定方向上的距离: (是否错开, 间距, 错位距离)，不在该方向时返回 None
+fn directional_distance(anchor: &Bounds, target: &Bounds, relation: Relation) -> Option<(bool, i32, i32)> {
+    let (a, t) = (anchor.center(), target.center());
+    let horizontal_overlap = target.x1 < anchor.x2 && target.x2 > anchor.x1;
+    let vertical_overlap = target.y1 < anchor.y2 && target.y2 > anchor.y1;
+
+    let (beyond, gap, aligned, offset) = match relation {
+        Relation::RightOf => (t.x >= anchor.x2, target.x1 - anchor.x2, vertical_overlap, t.y - a.y),
+        Relation::LeftOf => (t.x <= anchor.x1, anchor.x1 - target.x2, vertical_overlap, t.y - a.y),
+        Relation::Below => (t.y >= anchor.y2, target.y1 - anchor.y2, horizontal_overlap, t.x - a.x),
+        Relation::Above => (t.y <= anchor.y1, anchor.y1 - target.y2, horizontal_overlap, t.x - a.x),
+        Relation::Inside | Relation::SameGroup => return None,
+    };
+
+    beyond.then_some((!aligned, gap.max(0), offset.abs()))
+}
+
+/// 两个元素的最近公共祖先的层级深度（元素本身也算作自己的祖先）
+fn common_ancestor_depth(elements: &[UIElement], a: usize, b: usize) -> Option<usize> {
+    let ancestors = |index: usize| std::iter::successors(Some(index), |i| elements.get(*i).and_then(|e| e.parent_index));
+    let anchor_chain: HashSet<usize> = ancestors(a).collect();
+    ancestors(b)
+        .find(|i| anchor_chain.contains(i))
+        .and_then(|i| elements.get(i))
+        .map(|e| e.depth)
+}
+
+/// 相对定位关系的名称（用于错误信息）
+fn relation_name(relation: Relation) -> &'static str {
+    match relation {
+        Relation::RightOf => "右侧",
+        Relation::LeftOf => "左侧",
+        Relation::Above => "上方",
+        Relation::Below => "下方",
+        Relation::Inside => "内部",
+        Relation::SameGroup => "同组",
+    }
+}
+
+/// 🔥 使用单一策略查找（严格匹配，不使用 contains），返回所有匹配的元素
+fn find_by_single_strategy<'a>(
+    elements: &'a [UIElement],
     locator: &Locator,
     strategy: &str
-) -> Result<UIElement> {
+) -> Result<Vec<&'a UIElement>> {
     debug!("  - 使用单一策略: {}", strategy);
 
-    let result = match strategy {
+    let matches = match strategy {
         "resourceId" => {
             if let Some(ref resource_id) = locator.resource_id {
                 find_by_resource_id_strict(elements, resource_id)
@@ -122,13 +390,15 @@ fn find_by_single_strategy(
         }
     };
 
-    result.ok_or_else(|| {
-        TkeError::ElementNotFound(format!("使用 {} 策略未找到匹配元素", strategy))
-    })
+    if matches.is_empty() {
+        return Err(TkeError::ElementNotFound(format!("使用 {} 策略未找到匹配元素", strategy)));
+    }
+
+    Ok(matches)
 }
 
-/// 🔥 全精确匹配（所有 locator 字段都必须完全匹配）
-fn find_by_exact_match_strict(elements: &[UIElement], locator: &Locator) -> Result<UIElement> {
+/// 🔥 全精确匹配（所有 locator 字段都必须完全匹配），返回所有匹配的元素
+fn find_by_exact_match_strict<'a>(elements: &'a [UIElement], locator: &Locator) -> Result<Vec<&'a UIElement>> {
     let matches: Vec<&UIElement> = elements.iter().filter(|e| {
         // 所有非空字段都必须精确匹配
         (locator.text.is_none() || e.text.as_ref() == locator.text.as_ref()) &&
@@ -147,91 +417,151 @@ fn find_by_exact_match_strict(elements: &[UIElement], locator: &Locator) -> Resu
         ));
     }
 
-    // 🔥 如果找到多个匹配，警告并返回第一个
-    if matches.len() > 1 {
-        debug!("⚠️ 警告: 找到 {} 个匹配元素，将使用第一个", matches.len());
-        for (idx, element) in matches.iter().enumerate() {
-            debug!("  [{}/{}] text={:?}, resource_id={:?}, class={}, bounds={:?}",
-                   idx + 1, matches.len(),
-                   element.text, element.resource_id, element.class_name, element.bounds);
-        }
-    } else {
-        debug!("✅ 找到唯一匹配元素: text={:?}, resource_id={:?}",
-               matches[0].text, matches[0].resource_id);
-    }
-
-    Ok(matches[0].clone())
+    Ok(matches)
 }
 
 // ========== 严格匹配函数（不使用 contains 模糊匹配）==========
 
-fn find_by_resource_id_strict(elements: &[UIElement], resource_id: &str) -> Option<UIElement> {
-    let matches: Vec<&UIElement> = elements.iter().filter(|e| {
-        e.resource_id.as_ref() == Some(&resource_id.to_string())
-    }).collect();
+fn find_by_resource_id_strict<'a>(elements: &'a [UIElement], resource_id: &str) -> Vec<&'a UIElement> {
+    elements.iter().filter(|e| e.resource_id.as_deref() == Some(resource_id)).collect()
+}
 
-    if matches.is_empty() {
-        return None;
-    }
+fn find_by_xpath_strict<'a>(elements: &'a [UIElement], xpath: &str) -> Vec<&'a UIElement> {
+    elements.iter().filter(|e| e.xpath.as_deref() == Some(xpath)).collect()
+}
 
-    if matches.len() > 1 {
-        debug!("⚠️ resourceId='{}' 找到 {} 个匹配，使用第一个", resource_id, matches.len());
-    }
+fn find_by_text_strict<'a>(elements: &'a [UIElement], text: &str) -> Vec<&'a UIElement> {
+    elements.iter().filter(|e| e.text.as_deref() == Some(text)).collect()
+}
 
-    Some(matches[0].clone())
+fn find_by_class_name_strict<'a>(elements: &'a [UIElement], class_name: &str) -> Vec<&'a UIElement> {
+    elements.iter().filter(|e| e.class_name == class_name).collect()
 }
 
-fn find_by_xpath_strict(elements: &[UIElement], xpath: &str) -> Option<UIElement> {
-    elements.iter().find(|e| {
-        e.xpath.as_ref() == Some(&xpath.to_string())
-    }).cloned()
+fn find_by_content_desc_strict<'a>(elements: &'a [UIElement], content_desc: &str) -> Vec<&'a UIElement> {
+    elements.iter().filter(|e| e.content_desc.as_deref() == Some(content_desc)).collect()
 }
 
-fn find_by_text_strict(elements: &[UIElement], text: &str) -> Option<UIElement> {
-    let matches: Vec<&UIElement> = elements.iter().filter(|e| {
-        e.text.as_ref() == Some(&text.to_string())
-    }).collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 在临时项目目录中写入当前UI树，判断 resource-id 为 id 的元素是否可见
+    fn visible(name: &str, nodes: &str, id: &str) -> bool {
+        let dir = std::env::temp_dir().join(format!("tke_xml_visible_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("workarea")).unwrap();
+        let xml = format!(r#"<?xml version="1.0" encoding="UTF-8"?><hierarchy rotation="0">{}</hierarchy>"#, nodes);
+        std::fs::write(dir.join("workarea").join("current_ui_tree.xml"), xml).unwrap();
+
+        let locator: Locator = serde_json::from_value(serde_json::json!({ "type": "xml", "resource_id": id })).unwrap();
+        let locators = HashMap::from([("目标".to_string(), locator)]);
+        let result = is_visible(&dir, &locators, "目标", Some("resourceId"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap()
+    }
 
-    if matches.is_empty() {
-        return None;
+    const BUTTON: &str = r#"<node text="确定" resource-id="app:id/ok" class="android.widget.Button" clickable="true" bounds="[100,1000][500,1100]"><node text="确定" class="android.widget.TextView" bounds="[100,1000][500,1100]" /></node>"#;
+
+    fn screen(children: &str) -> String {
+        format!(r#"<node class="android.widget.FrameLayout" bounds="[0,0][1080,1920]">{}</node>"#, children)
     }
 
-    if matches.len() > 1 {
-        debug!("⚠️ text='{}' 找到 {} 个匹配，使用第一个", text, matches.len());
+    #[test]
+    fn plain_element_is_visible() {
+        assert!(visible("plain", &screen(BUTTON), "app:id/ok"));
     }
 
-    Some(matches[0].clone())
-}
+    #[test]
+    fn container_drawn_later_occludes_smaller_element() {
+        // 全屏遮罩没有文本也不可点击，面积比按钮大，但绘制在按钮之上
+        let scrim = r#"<node class="android.view.View" resource-id="app:id/scrim" bounds="[0,0][1080,1920]" />"#;
+        assert!(!visible("scrim", &screen(&format!("{}{}", BUTTON, scrim)), "app:id/ok"));
 
-fn find_by_class_name_strict(elements: &[UIElement], class_name: &str) -> Option<UIElement> {
-    let matches: Vec<&UIElement> = elements.iter().filter(|e| {
-        &e.class_name == class_name
-    }).collect();
+        // 同一个遮罩排在按钮之前时绘制在下层，不遮挡
+        assert!(visible("scrim_below", &screen(&format!("{}{}", scrim, BUTTON)), "app:id/ok"));
+    }
 
-    if matches.is_empty() {
-        return None;
+    #[test]
+    fn partial_cover_and_own_children_do_not_occlude() {
+        let badge = r#"<node text="1" class="android.widget.TextView" bounds="[400,1000][500,1050]" />"#;
+        assert!(visible("badge", &screen(&format!("{}{}", BUTTON, badge)), "app:id/ok"));
     }
 
-    if matches.len() > 1 {
-        debug!("⚠️ className='{}' 找到 {} 个匹配，使用第一个", class_name, matches.len());
+    #[test]
+    fn element_outside_screen_is_not_visible() {
+        let outside = r#"<node text="外部" resource-id="app:id/out" class="android.widget.Button" bounds="[-600,100][-100,200]" />"#;
+        assert!(!visible("outside", &screen(outside), "app:id/out"));
+        assert!(!visible("missing", &screen(BUTTON), "app:id/missing"));
     }
 
-    Some(matches[0].clone())
-}
+    // 登录表单：两行，每行一个标签和一个输入框
+    const FORM: &str = r#"<?xml version="1.0" encoding="UTF-8"?><hierarchy rotation="0"><node class="android.widget.FrameLayout" bounds="[0,0][1080,1920]"><node class="android.widget.LinearLayout" resource-id="app:id/row_user" bounds="[0,100][1080,200]"><node text="用户名" class="android.widget.TextView" bounds="[0,100][300,200]" /><node resource-id="app:id/user" class="android.widget.EditText" clickable="true" bounds="[300,100][1080,200]" /></node><node class="android.widget.LinearLayout" resource-id="app:id/row_pwd" bounds="[0,300][1080,400]"><node text="密码" class="android.widget.TextView" bounds="[0,300][300,400]" /><node resource-id="app:id/pwd" class="android.widget.EditText" clickable="true" bounds="[300,300][1080,400]" /></node></node></hierarchy>"#;
 
-fn find_by_content_desc_strict(elements: &[UIElement], content_desc: &str) -> Option<UIElement> {
-    let matches: Vec<&UIElement> = elements.iter().filter(|e| {
-        e.content_desc.as_ref() == Some(&content_desc.to_string())
-    }).collect();
+    fn form_elements() -> Vec<UIElement> {
+        let mut fetcher = Fetcher::new();
+        fetcher.set_keep_containers(true);
+        fetcher.fetch_elements_from_xml(FORM).unwrap()
+    }
 
-    if matches.is_empty() {
-        return None;
+    fn by_id<'a>(elements: &'a [UIElement], id: &str) -> &'a UIElement {
+        elements.iter().find(|e| e.resource_id.as_deref() == Some(id)).unwrap()
     }
 
-    if matches.len() > 1 {
-        debug!("⚠️ contentDesc='{}' 找到 {} 个匹配，使用第一个", content_desc, matches.len());
+    fn by_text<'a>(elements: &'a [UIElement], text: &str) -> &'a UIElement {
+        elements.iter().find(|e| e.text.as_deref() == Some(text)).unwrap()
     }
 
-    Some(matches[0].clone())
-}
+    #[test]
+    fn directional_distance_prefers_aligned_targets() {
+        let anchor = Bounds::new(0, 100, 200, 200);
+
+        // 同一行: 间距100，没有错位
+        let same_row = directional_distance(&anchor, &Bounds::new(300, 100, 500, 200), Relation::RightOf);
+        assert_eq!(same_row, Some((false, 100, 0)));
+
+        // 下一行更近，但与锚点错开，排在同一行之后
+        let next_row = directional_distance(&anchor, &Bounds::new(250, 400, 450, 500), Relation::RightOf);
+        assert_eq!(next_row, Some((true, 50, 300)));
+        assert!(same_row < next_row);
+
+        // 与锚点重叠时间距为0；中心没有越过锚点的边时不在该方向
+        assert_eq!(directional_distance(&anchor, &Bounds::new(150, 100, 400, 200), Relation::RightOf), Some((false, 0, 0)));
+        assert_eq!(directional_distance(&anchor, &Bounds::new(300, 100, 500, 200), Relation::LeftOf), None);
+        assert_eq!(directional_distance(&anchor, &Bounds::new(0, 300, 200, 400), Relation::Below), Some((false, 100, 0)));
+        assert_eq!(directional_distance(&anchor, &Bounds::new(0, 300, 200, 400), Relation::Above), None);
+        assert_eq!(directional_distance(&anchor, &Bounds::new(0, 300, 200, 400), Relation::Inside), None);
+    }
+
+    #[test]
+    fn common_ancestor_is_deeper_within_the_same_row() {
+        let elements = form_elements();
+        let label = by_text(&elements, "密码");
+
+        let same_row = common_ancestor_depth(&elements, label.index, by_id(&elements, "app:id/pwd").index);
+        let other_row = common_ancestor_depth(&elements, label.index, by_id(&elements, "app:id/user").index);
+        assert!(same_row.unwrap() > other_row.unwrap());
 
+        // 元素本身也算作自己的祖先
+        assert_eq!(common_ancestor_depth(&elements, label.index, label.index), Some(label.depth));
+    }
+
+    #[test]
+    fn pick_relative_selects_input_for_label() {
+        let elements = form_elements();
+        let inputs: Vec<&UIElement> = elements.iter().filter(|e| e.class_name == "android.widget.EditText").collect();
+        let picked = |relation, anchor: &UIElement| {
+            pick_relative(&elements, &inputs, relation, anchor).and_then(|e| e.resource_id.clone())
+        };
+
+        let user_label = by_text(&elements, "用户名");
+        let pwd_label = by_text(&elements, "密码");
+        assert_eq!(picked(Relation::RightOf, pwd_label).as_deref(), Some("app:id/pwd"));
+        assert_eq!(picked(Relation::Below, user_label).as_deref(), Some("app:id/pwd"));
+        assert_eq!(picked(Relation::LeftOf, pwd_label), None);
+        assert_eq!(picked(Relation::SameGroup, pwd_label).as_deref(), Some("app:id/pwd"));
+        assert_eq!(picked(Relation::Inside, by_id(&elements, "app:id/row_user")).as_deref(), Some("app:id/user"));
+
+        // 锚点本身不参与选择，只剩下另一行的输入框
+        assert_eq!(picked(Relation::SameGroup, by_id(&elements, "app:id/user")).as_deref(), Some("app:id/pwd"));
+    }
+}
@@ -0,0 +1,195 @@
+// 数据驱动 - 加载 数据: 引用的 CSV/JSON 文件，并检查数据表
+// - CSV: 第一行为列名，支持双引号包裹含逗号或换行的值（"" 表示引号）
+// - JSON: 对象数组 [{"用户名": "admin", "密码": "123"}, ...]，列为所有对象中出现的键
+
+use super::parser::ScriptParser;
+use crate::{Result, TkeError, TksData};
+use std::path::{Path, PathBuf};
+
+impl ScriptParser {
+    // 加载数据文件（内联表格无需加载），并检查每行的列数与表头一致
+    pub(super) fn load_data(&self, data: &mut TksData, script_path: Option<&PathBuf>) -> Result<()> {
+        let line_number = data.line_number;
+        let error = |message: String| {
+            TkeError::ScriptParseError(format!("第{}行: {}", line_number, message))
+        };
+
+        if let Some(ref source) = data.source {
+            let path = self.resolve_data_path(source, script_path)
+                .ok_or_else(|| error(format!("找不到数据文件 '{}'", source)))?;
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| error(format!("读取数据文件 '{}' 失败: {}", source, e)))?;
+
+            let (columns, rows) = match path.extension().and_then(|s| s.to_str()) {
+                Some("csv") => parse_csv(&content),
+                Some("json") => parse_json(&content)
+                    .map_err(|message| error(format!("数据文件 '{}' 格式错误: {}", source, message)))?,
+                _ => return Err(error(format!("数据文件 '{}' 只支持 .csv 和 .json", source))),
+            };
+            data.columns = columns;
+            data.rows = rows;
+        }
+
+        if data.columns.is_empty() || data.rows.is_empty() {
+            return Err(error("数据表至少需要列名和一行数据".to_string()));
+        }
+        if data.columns.iter().any(|c| c.is_empty()) {
+            return Err(error("数据表的列名不能为空".to_string()));
+        }
+        for (index, row) in data.rows.iter().enumerate() {
+            if row.len() != data.columns.len() {
+                return Err(error(format!(
+                    "数据第{}行有 {} 列，表头有 {} 列", index + 1, row.len(), data.columns.len()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // 解析数据文件路径：绝对路径 > 相对用例文件夹 > 相对脚本所在目录 > 相对项目目录
+    fn resolve_data_path(&self, source: &str, script_path: Option<&PathBuf>) -> Option<PathBuf> {
+        let source = Path::new(source);
+        if source.is_absolute() {
+            return source.is_file().then(|| source.to_path_buf());
+        }
+
+        let script_dir = script_path.and_then(|p| p.parent());
+        let candidates = [
+            script_dir.and_then(|dir| dir.parent()).map(|case_dir| case_dir.join(source)),
+            script_dir.map(|dir| dir.join(source)),
+            self.project_path().map(|p| p.join(source)),
+        ];
+
+        candidates.into_iter().flatten().find(|p| p.is_file())
+    }
+}
+
+// 解析 CSV 内容，返回 (列名, 数据行)；忽略空行
+fn parse_csv(content: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut records: Vec<Vec<String>> = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            ch => field.push(ch),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // 去掉空行（只有一个空字段的记录）
+    let mut records = records.into_iter()
+        .filter(|r| !(r.len() == 1 && r[0].trim().is_empty()))
+        .map(|r| r.into_iter().map(|f| f.trim().to_string()).collect::<Vec<_>>());
+
+    let columns = records.next().unwrap_or_default();
+    (columns, records.collect())
+}
+
+// 解析 JSON 对象数组，返回 (列名, 数据行)；缺少的键为空字符串
+fn parse_json(content: &str) -> std::result::Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let items = value.as_array().ok_or("应为对象数组")?;
+
+    let mut columns: Vec<String> = Vec::new();
+    for item in items {
+        let object = item.as_object().ok_or("数组元素应为对象")?;
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+
+    let rows = items.iter()
+        .map(|item| columns.iter()
+            .map(|column| match &item[column] {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect())
+        .collect();
+
+    Ok((columns, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_header_and_rows() {
+        let (columns, rows) = parse_csv("名称,用户名,密码\n管理员,admin,123456\n访客,guest,654321");
+
+        assert_eq!(columns, strings(&["名称", "用户名", "密码"]));
+        assert_eq!(rows, vec![
+            strings(&["管理员", "admin", "123456"]),
+            strings(&["访客", "guest", "654321"]),
+        ]);
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_quotes_and_newlines() {
+        let (_, rows) = parse_csv("名称,备注\n引号,\"他说\"\"好\"\"\"\n逗号,\"a,b\"\n换行,\"第一行\n第二行\"\n");
+
+        assert_eq!(rows, vec![
+            strings(&["引号", "他说\"好\""]),
+            strings(&["逗号", "a,b"]),
+            strings(&["换行", "第一行\n第二行"]),
+        ]);
+    }
+
+    #[test]
+    fn ignores_bom_crlf_and_blank_lines() {
+        let (columns, rows) = parse_csv("\u{feff}名称, 值\r\n\r\n甲, 1\r\n\n乙,\r\n");
+
+        assert_eq!(columns, strings(&["名称", "值"]));
+        assert_eq!(rows, vec![strings(&["甲", "1"]), strings(&["乙", ""])]);
+    }
+
+    #[test]
+    fn json_columns_are_union_of_keys() {
+        let (columns, rows) = parse_json(r#"[{"用户名": "admin", "重试": 3}, {"用户名": "guest", "记住": true}]"#).unwrap();
+
+        assert_eq!(columns, strings(&["用户名", "重试", "记住"]));
+        assert_eq!(rows, vec![strings(&["admin", "3", ""]), strings(&["guest", "", "true"])]);
+        assert!(parse_json(r#"{"用户名": "admin"}"#).is_err());
+    }
+
+    #[test]
+    fn load_data_rejects_rows_with_wrong_column_count() {
+        let mut data = TksData {
+            source: None,
+            columns: strings(&["名称", "值"]),
+            rows: vec![strings(&["甲", "1"]), strings(&["乙"])],
+            line_number: 4,
+        };
+
+        let error = ScriptParser::new().load_data(&mut data, None).unwrap_err().to_string();
+        assert!(error.contains("第4行") && error.contains("数据第2行有 1 列"), "{}", error);
+    }
+}
@@ -0,0 +1,227 @@
+// 脚本诊断 - 严格解析模式下的问题报告与命令参数签名表
+
+use crate::{TksCommand, TksParam, Locale};
+use super::keywords::KeywordTable;
+use serde::Serialize;
+
+// 诊断级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// 单条诊断信息
+// line 从1开始；start/end 为该行内的字符列（从0开始，end 不包含）
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,  // 建议替换为的文本（如 "点击"）
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, start: usize, end: usize, message: impl Into<String>) -> Self {
+        Self { line, start, end, severity: Severity::Error, message: message.into(), suggestion: None }
+    }
+
+    pub fn warning(line: usize, start: usize, end: usize, message: impl Into<String>) -> Self {
+        Self { line, start, end, severity: Severity::Warning, message: message.into(), suggestion: None }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+// 参数类型（与解释器中 extract_* / resolve_target 接受的参数一致）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Target,         // 坐标 / XML元素 / 图片元素
+    Element,        // XML元素 / 图片元素
+    XmlElement,     // XML元素
+    Findable,       // XML元素 / 图片元素 / 文本
+    Readable,       // 坐标 / XML元素（读取区域）
+    Text,           // 文本
+    Number,         // 数字
+    Duration,       // 持续时间（10s 或毫秒数）
+    Direction,      // 方向 上/下/左/右
+    Key,            // 按键名称或键码（HOME / 3 / CTRL_LEFT+A）
+    LongPress,      // 长按选项
+    State,          // 存在/不存在/可见/不可见，或属性条件（已勾选/文本包含=... 等）
+    Value,          // 变量值（文本、数字、时间、布尔、方向）
+    WaitTarget,     // 等待: 时间、元素或界面稳定
+    WaitOption,     // 等待: 元素状态或界面稳定的连续次数
+}
+
+impl ParamKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ParamKind::Target => "坐标或元素",
+            ParamKind::Element => "元素",
+            ParamKind::XmlElement => "XML元素",
+            ParamKind::Findable => "元素或文本",
+            ParamKind::Readable => "坐标或XML元素",
+            ParamKind::Text => "文本",
+            ParamKind::Number => "数字",
+            ParamKind::Duration => "持续时间",
+            ParamKind::Direction => "方向(上/下/左/右)",
+            ParamKind::Key => "按键名称或键码",
+            ParamKind::LongPress => "长按(长按/long_press)",
+            ParamKind::State => "存在/不存在/可见/不可见或属性条件",
+            ParamKind::Value => "文本或数字",
+            ParamKind::WaitTarget => "时间、元素或界面稳定",
+            ParamKind::WaitOption => "存在/不存在/可见/不可见或次数",
+        }
+    }
+
+    // 检查参数是否符合该类型（含 ${变量} 的文本在执行时才确定，视为可接受）
+    pub fn accepts(&self, param: &TksParam) -> bool {
+        let is_variable = matches!(param, TksParam::Text(t) if t.contains("${"));
+
+        match self {
+            ParamKind::Target => matches!(param,
+                TksParam::Coordinate(_) | TksParam::XmlElement { .. } | TksParam::ImageElement(_)),
+            ParamKind::Element => matches!(param,
+                TksParam::XmlElement { .. } | TksParam::ImageElement(_)),
+            ParamKind::XmlElement => matches!(param, TksParam::XmlElement { .. }),
+            ParamKind::Findable => matches!(param,
+                TksParam::XmlElement { .. } | TksParam::ImageElement(_) | TksParam::Text(_)),
+            ParamKind::Readable => matches!(param,
+                TksParam::Coordinate(_) | TksParam::XmlElement { .. }),
+            ParamKind::Text => matches!(param, TksParam::Text(_)),
+            ParamKind::Number => is_variable || matches!(param, TksParam::Number(_)),
+            ParamKind::Duration => is_variable || matches!(param,
+                TksParam::Duration(_) | TksParam::Number(_)),
+            ParamKind::Direction => is_variable || match param {
+                TksParam::Direction(_) => true,
+                TksParam::Text(t) => matches!(t.as_str(), "up" | "down" | "left" | "right"),
+                _ => false,
+            },
+            ParamKind::Key => matches!(param, TksParam::Text(_) | TksParam::Number(_)),
+            ParamKind::LongPress => matches!(param, TksParam::Text(t) if KeywordTable::is_long_press(t)),
+            ParamKind::State => matches!(param,
+                TksParam::Boolean(_) | TksParam::Visibility(_) | TksParam::Attribute(_)),
+            ParamKind::Value => matches!(param,
+                TksParam::Text(_) | TksParam::Number(_) | TksParam::Duration(_) |
+                TksParam::Boolean(_) | TksParam::Direction(_)),
+            ParamKind::WaitTarget => is_variable || matches!(param,
+                TksParam::Duration(_) | TksParam::Number(_) |
+                TksParam::XmlElement { .. } | TksParam::ImageElement(_))
+                || matches!(param, TksParam::Text(t) if KeywordTable::is_ui_idle(t)),
+            ParamKind::WaitOption => matches!(param,
+                TksParam::Boolean(_) | TksParam::Visibility(_) | TksParam::Number(_)),
+        }
+    }
+}
+
+// 命令参数签名
+pub struct CommandSignature {
+    pub params: &'static [(&'static str, ParamKind)],  // (参数名, 类型)
+    pub required: usize,                               // 必填参数个数
+    pub variadic: bool,                                // 是否允许额外参数（调用 的子脚本参数）
+}
+
+// 获取命令的参数签名
+pub fn signature(command: &TksCommand) -> CommandSignature {
+    use ParamKind::*;
+
+    let (params, required, variadic): (&'static [(&'static str, ParamKind)], usize, bool) = match command {
+        TksCommand::Launch => (&[("包名", Text), ("Activity", Text)], 2, false),
+        TksCommand::Close | TksCommand::Uninstall | TksCommand::ClearData => (&[("包名", Text)], 1, false),
+        TksCommand::Install => (&[("APK路径", Text), ("选项", Text)], 1, false),
+        TksCommand::Grant | TksCommand::Revoke => (&[("包名", Text), ("权限", Text)], 2, false),
+        TksCommand::Click => (&[("目标", Target)], 1, false),
+        TksCommand::Press => (&[("目标", Target), ("时长", Duration)], 1, false),
+        TksCommand::Swipe => (&[("起点", Target), ("终点", Target), ("时长", Duration)], 2, false),
+        TksCommand::DirectionalSwipe | TksCommand::DirectionalDrag => (
+            &[("起点", Target), ("方向", Direction), ("距离", Number), ("时长", Duration)], 3, false),
+        TksCommand::ScrollFind => (
+            &[("目标", Findable), ("方向", Direction), ("容器", XmlElement), ("最大次数", Number)], 1, false),
+        TksCommand::Drag => (&[("目标", Target), ("终点", Target), ("时长", Duration)], 2, false),
+        TksCommand::PinchIn | TksCommand::PinchOut => (&[("目标", Target), ("距离", Number), ("时长", Duration)], 1, false),
+        TksCommand::Rotate => (&[("目标", Target), ("角度", Number), ("时长", Duration)], 2, false),
+        TksCommand::MultiSwipe => (
+            &[("起点", Target), ("方向", Direction), ("距离", Number), ("手指数", Number), ("时长", Duration)], 3, false),
+        TksCommand::Input => (&[("目标", Target), ("文本", Text)], 2, false),
+        TksCommand::Clear => (&[("目标", Target)], 0, false),
+        TksCommand::HideKeyboard | TksCommand::Back => (&[], 0, false),
+        TksCommand::Key => (&[("按键", Key), ("长按", LongPress)], 1, false),
+        TksCommand::Screenshot => (&[("名称", Value)], 0, false),
+        TksCommand::Shell => (&[("命令", Text), ("输出正则", Text)], 1, false),
+        TksCommand::Wait => (
+            &[("时长或元素", WaitTarget), ("状态或次数", WaitOption), ("超时", Duration), ("间隔", Duration)], 0, false),
+        TksCommand::Assert => (&[("目标", Element), ("条件", State)], 2, false),
+        TksCommand::Read => (&[("目标", Readable), ("宽度", Number), ("高度", Number)], 1, false),
+        TksCommand::SetVariable => (&[("变量名", Text), ("值", Value)], 2, false),
+        TksCommand::If => (&[("目标", Readable), ("条件", State)], 1, false),
+        TksCommand::Repeat => (&[("次数", Number)], 1, false),
+        TksCommand::RepeatUntil => (&[("目标", Readable), ("条件", State), ("最大次数", Number)], 2, false),
+        TksCommand::Call => (&[("子脚本", Text)], 1, true),
+    };
+
+    CommandSignature { params, required, variadic }
+}
+
+// 命令用法（如 "按压 [目标, 时长?]"），可选参数以 ? 结尾；命令名按指定语言输出
+pub fn usage(command: &TksCommand, locale: Locale) -> String {
+    let signature = signature(command);
+    let mut params: Vec<String> = signature.params.iter()
+        .enumerate()
+        .map(|(index, (name, _))| {
+            if index < signature.required {
+                name.to_string()
+            } else {
+                format!("{}?", name)
+            }
+        })
+        .collect();
+    if signature.variadic {
+        params.push("参数...".to_string());
+    }
+
+    if params.is_empty() {
+        command.keyword(locale).to_string()
+    } else {
+        format!("{} [{}]", command.keyword(locale), params.join(", "))
+    }
+}
+
+// 计算两个字符串的编辑距离（按字符）
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (prev[j] + 1).min(current[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = current;
+    }
+
+    prev[b.len()]
+}
+
+// 从候选项中找出最接近的一个（编辑距离不超过名称长度的一半）
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 2).max(1);
+
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, candidate)| (*distance, *candidate))
+        .map(|(_, candidate)| candidate)
+}
@@ -0,0 +1,402 @@
+// 脚本格式化 - 将 .tks 脚本重新输出为统一的格式（与 Studio 编辑器生成的格式一致）
+// - 头部: "键: 值"，详情内容和数据表格缩进4个空格
+// - 步骤: 每层缩进4个空格，块命令的子步骤再缩进一层
+// - 命令: "命令 [参数1, 参数2]"，无参数时只写命令名，坐标写作 {x,y}
+// - 保留注释和空行（连续空行合并为一行），无法识别的行原样保留
+// - 步骤修饰符（重试=3 超时=10s 允许失败）放在行尾，期望值之后
+// - 指定语言时，命令、方向、状态、属性条件、相对定位、块结构、头部字段、修饰符、长按和界面稳定关键字统一转换为该语言
+
+use super::diagnostic::ParamKind;
+use super::keywords::{KeywordTable, BlockKeyword, Section};
+use super::parser::{ScriptParser, HeaderBlock};
+use crate::{TksCommand, TksParam, Locale};
+
+// 每层缩进
+const INDENT: &str = "    ";
+
+impl ScriptParser {
+    // 格式化脚本内容，结果以换行结尾；对已格式化的内容再次格式化不会产生变化
+    // locale 为 None 时保留脚本原有的关键字写法
+    pub fn format(&self, content: &str, locale: Option<Locale>) -> String {
+        // 次数后缀（重复 N 次 / repeat N times）跟随脚本语言
+        let count_locale = locale.unwrap_or_else(|| self.keywords().detect_locale(content));
+        let mut lines: Vec<String> = Vec::new();
+        let mut in_steps = false;
+        let mut header_block = HeaderBlock::None;
+        // 当前步骤的块嵌套深度
+        let mut depth = 0usize;
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+
+            // 空行：去掉开头的空行，连续空行只保留一行
+            if trimmed.is_empty() {
+                if lines.last().is_some_and(|last| !last.is_empty()) {
+                    lines.push(String::new());
+                }
+                continue;
+            }
+
+            // 前置/步骤/后置 段落标记，块嵌套深度从头开始
+            if let Some(section) = self.keywords().step_section(trimmed) {
+                in_steps = true;
+                depth = 0;
+                lines.push(match locale {
+                    Some(locale) => format!("{}:", KeywordTable::section_keyword(section, locale)),
+                    None => trimmed.to_string(),
+                });
+                continue;
+            }
+
+            if !in_steps {
+                if trimmed.starts_with('#') {
+                    lines.push(trimmed.to_string());
+                } else {
+                    lines.push(self.format_header_line(trimmed, locale, &mut header_block));
+                }
+                continue;
+            }
+
+            let indent = INDENT.repeat(depth + 1);
+            let keyword = |block: BlockKeyword| match locale {
+                Some(locale) => KeywordTable::block_keyword(block, locale),
+                None => trimmed,
+            };
+            match self.keywords().block(trimmed) {
+                Some(BlockKeyword::Else) => {
+                    lines.push(format!("{}{}", INDENT.repeat(depth.max(1)), keyword(BlockKeyword::Else)));
+                }
+                Some(BlockKeyword::End) => {
+                    depth = depth.saturating_sub(1);
+                    lines.push(format!("{}{}", INDENT.repeat(depth + 1), keyword(BlockKeyword::End)));
+                }
+                None if trimmed.starts_with('#') => lines.push(format!("{}{}", indent, trimmed)),
+                None => {
+                    let (formatted, command) = self.format_step(trimmed, locale, count_locale);
+                    lines.push(format!("{}{}", indent, formatted));
+                    if command.is_some_and(|c| c.is_block()) {
+                        depth += 1;
+                    }
+                }
+            }
+        }
+
+        while lines.last().is_some_and(|last| last.is_empty()) {
+            lines.pop();
+        }
+
+        let mut formatted = lines.join("\n");
+        formatted.push('\n');
+        formatted
+    }
+
+    // 格式化头部的一行（与 parse_header_line 的识别规则一致）
+    fn format_header_line(&self, line: &str, locale: Option<Locale>, block: &mut HeaderBlock) -> String {
+        // 数据表格行: "| 列1 | 列2 |"
+        if *block == HeaderBlock::Data && line.starts_with('|') {
+            return format!("{}| {} |", INDENT, Self::table_cells(line).join(" | "));
+        }
+
+        let (key, value) = match line.split_once(':').or_else(|| line.split_once('：')) {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (line, None),
+        };
+
+        let field = |key: &str, value: &str| {
+            if value.is_empty() {
+                format!("{}:", key)
+            } else {
+                format!("{}: {}", key, value)
+            }
+        };
+
+        // 头部字段按指定语言输出
+        let section = self.keywords().section(key);
+        let name = match (section, locale) {
+            (Some(section), Some(locale)) => KeywordTable::section_keyword(section, locale),
+            _ => key,
+        };
+
+        match (section, value) {
+            (Some(Section::CaseId | Section::ScriptName | Section::Priority), Some(value)) => {
+                *block = HeaderBlock::None;
+                field(name, value)
+            }
+            (Some(Section::Data), Some(value)) => {
+                *block = HeaderBlock::Data;
+                field(name, value)
+            }
+            (Some(Section::Tags), Some(value)) => {
+                *block = HeaderBlock::None;
+                let tags: Vec<&str> = value
+                    .split(|c: char| c == ',' || c == '，' || c.is_whitespace())
+                    .map(|t| t.trim())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                field(name, &tags.join(", "))
+            }
+            // 详情: 之后同一行的内容整体是描述文本，移到下一行会被当作 键: 值 解析，所以留在原行
+            (Some(Section::Details), Some(value)) => {
+                *block = HeaderBlock::Details;
+                field(name, value)
+            }
+            (_, Some(value)) if *block == HeaderBlock::Details && !key.is_empty() => {
+                format!("{}{}", INDENT, field(key, value))
+            }
+            _ if *block == HeaderBlock::Details => format!("{}{}", INDENT, line),
+            _ => {
+                *block = HeaderBlock::None;
+                line.to_string()
+            }
+        }
+    }
+
+    // 格式化单个步骤，返回 (格式化后的文本, 识别出的命令)；无法识别的步骤原样返回
+    fn format_step(&self, line: &str, locale: Option<Locale>, count_locale: Locale) -> (String, Option<TksCommand>) {
+        let Some(step) = self.parse_step(line, 0) else {
+            return (line.to_string(), None);
+        };
+
+        let (body, modifier_words) = self.split_modifiers(line);
+        let (body, expected) = self.split_expectation(body);
+        let Some((command_str, _, params_str, bracketed)) = Self::split_command(body) else {
+            return (line.to_string(), None);
+        };
+        // 方括号不匹配的行保持原样，避免改变含义
+        if !bracketed && params_str.trim_start().starts_with('[') {
+            return (line.to_string(), Some(step.command));
+        }
+
+        let params: Vec<String> = Self::split_parameters(params_str)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, raw))| match (&step.command, locale) {
+                // 按键的长按选项
+                (TksCommand::Key, Some(locale)) if KeywordTable::is_long_press(raw) => {
+                    KeywordTable::long_press_keyword(locale).to_string()
+                }
+                // 等待的界面稳定关键字
+                (TksCommand::Wait, Some(locale)) if KeywordTable::is_ui_idle(raw) => {
+                    KeywordTable::ui_idle_keyword(locale).to_string()
+                }
+                _ => self.format_parameter(raw, Self::param_kind(&step.command, index), locale),
+            })
+            .collect();
+        let command_str = locale.map_or(command_str, |locale| step.command.keyword(locale));
+
+        let mut formatted = match (&step.command, step.params.as_slice()) {
+            // 固定次数循环写作 "重复 N 次" / "repeat N times"
+            (TksCommand::Repeat, [TksParam::Number(count)]) => {
+                format!("{} {} {}", command_str, count, KeywordTable::count_suffix(count_locale))
+            }
+            (TksCommand::Repeat, [TksParam::Text(count)]) if count.contains("${") => {
+                format!("{} {} {}", command_str, count, KeywordTable::count_suffix(count_locale))
+            }
+            (_, []) => command_str.to_string(),
+            _ => format!("{} [{}]", command_str, params.join(", ")),
+        };
+
+        if let Some(expected) = expected {
+            formatted.push_str(" == ");
+            formatted.push_str(&Self::format_text(&expected, false));
+        }
+
+        for word in modifier_words {
+            formatted.push(' ');
+            formatted.push_str(&self.format_modifier(word, locale));
+        }
+
+        (formatted, Some(step.command))
+    }
+
+    // 格式化单个参数（只调整不影响含义的写法），指定语言时转换方向、状态和属性条件关键字
+    // kind 为该位置的参数类型，与解析时一致（只在对应位置转换关键字）
+    fn format_parameter(&self, raw: &str, kind: Option<ParamKind>, locale: Option<Locale>) -> String {
+        match (self.parse_parameter(raw, kind), locale) {
+            (TksParam::Coordinate(point), _) => format!("{{{},{}}}", point.x, point.y),
+            (TksParam::Direction(direction), Some(locale)) => {
+                KeywordTable::direction_keyword(&direction, locale).unwrap_or(raw).to_string()
+            }
+            (TksParam::Boolean(_) | TksParam::Visibility(_), Some(locale)) => match self.keywords().state(raw) {
+                Some((visibility, expected)) => KeywordTable::state_keyword(visibility, expected, locale).to_string(),
+                None => raw.to_string(),
+            },
+            (TksParam::Attribute(condition), Some(locale)) => {
+                let keyword = KeywordTable::attribute_keyword(&condition, locale);
+                match raw.split_once('=') {
+                    Some((_, value)) => format!("{}={}", keyword, value.trim()),
+                    None => keyword.to_string(),
+                }
+            }
+            (TksParam::Attribute(_), None) => match raw.split_once('=') {
+                Some((key, value)) => format!("{}={}", key.trim(), value.trim()),
+                None => raw.to_string(),
+            },
+            // 相对定位: 目标、关系和锚点之间各保留一个空格
+            (TksParam::XmlElement { relative: Some(relative), .. }, _) => {
+                let (target, anchor) = Self::split_relative(raw);
+                let keyword = match locale {
+                    Some(locale) => KeywordTable::relation_keyword(relative.relation, locale),
+                    None => raw[target.len()..].split_whitespace().next().unwrap_or_default(),
+                };
+                format!("{} {} {}", target, keyword, anchor)
+            }
+            (TksParam::Text(text), _) if Self::is_quoted(raw) => {
+                // 去掉引号后含义不变时不需要引号（含引号或大括号的文本会影响参数拆分）
+                let unquoted = !text.contains(['"', '\'', '{', '}'])
+                    && matches!(self.parse_parameter(&text, kind), TksParam::Text(ref t) if *t == text);
+                Self::format_text(&text, !unquoted)
+            }
+            _ => raw.to_string(),
+        }
+    }
+
+    // 格式化步骤修饰符，指定语言时转换关键字（值保持原样）
+    fn format_modifier(&self, word: &str, locale: Option<Locale>) -> String {
+        let (key, value) = match word.split_once('=') {
+            Some((key, value)) => (key, Some(value.trim())),
+            None => (word, None),
+        };
+        let key = match (self.keywords().modifier(key), locale) {
+            (Some(modifier), Some(locale)) => KeywordTable::modifier_keyword(modifier, locale),
+            _ => key,
+        };
+
+        match value {
+            Some(value) => format!("{}={}", key, value),
+            None => key.to_string(),
+        }
+    }
+
+    // 输出文本，必要时加双引号（含逗号、首尾空白或为空）
+    fn format_text(text: &str, force_quotes: bool) -> String {
+        let needs_quotes = force_quotes
+            || text.is_empty()
+            || text.trim() != text
+            || text.contains(',');
+
+        if !needs_quotes {
+            text.to_string()
+        } else if !text.contains('"') {
+            format!("\"{}\"", text)
+        } else {
+            format!("'{}'", text)
+        }
+    }
+
+    fn is_quoted(raw: &str) -> bool {
+        raw.len() >= 2 &&
+            ((raw.starts_with('"') && raw.ends_with('"')) ||
+             (raw.starts_with('\'') && raw.ends_with('\'')))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TksScript, TksStep};
+    use std::collections::BTreeMap;
+
+    const SCRIPT: &str = "\
+# 登录用例
+用例: TC001
+脚本名:登录成功
+标签: 冒烟，登录  回归
+优先级: P0
+详情: 注意: 需要登录
+    前置条件 : 已安装App
+    没有冒号的描述
+
+
+数据:
+    |名称|用户名|
+    |---|---|
+    | 管理员 | admin |
+
+前置:
+    启动 [com.example.app, .MainActivity]
+
+步骤:
+  点击 [{登录按钮}]
+    输入 [{用户名输入框}, ${用户名}]
+    输入 [{备注},可见]
+    设置 [方向, up]
+    如果 [{弹窗}, 存在]
+        点击 [{关闭}]
+    否则
+        返回
+    结束
+    重复 3 次
+        定向滑动 [{500, 1500}, 上, 800]
+    结束
+    重复 ${次数} 次
+        点击 [{200,400}]
+    结束
+    等待 [{加载}, 不可见, 5s]
+    断言 [{开关}, 已勾选]
+    读取 [{标题}] == 欢迎 重试=2 超时=5s
+    点击 [{广告关闭按钮}] 允许失败
+    点击 [{开关} 右侧 {Wi-Fi标签}]
+
+后置:
+    关闭 [com.example.app]
+";
+
+    // 语法树中与书写格式无关的部分（去掉原始文本和行号）
+    fn step_tree(steps: &[TksStep]) -> Vec<String> {
+        steps.iter()
+            .map(|step| format!("{:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+                step.command, step.params, step.expected, step.modifiers, step.callee,
+                step_tree(&step.body), step_tree(&step.else_body)))
+            .collect()
+    }
+
+    fn tree(content: &str) -> String {
+        let script: TksScript = ScriptParser::new().parse(content).unwrap();
+        let details: BTreeMap<_, _> = script.details.iter().collect();
+        format!("{} {} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+            script.case_id, script.script_name, details, script.tags, script.priority,
+            script.data.as_ref().map(|data| (&data.source, &data.columns, &data.rows)),
+            step_tree(&script.setup), step_tree(&script.steps), step_tree(&script.teardown))
+    }
+
+    // 格式化前后解析结果相同，且再次格式化不再变化
+    fn assert_round_trip(content: &str, locale: Option<Locale>) -> String {
+        let parser = ScriptParser::new();
+        let formatted = parser.format(content, locale);
+        assert_eq!(tree(content), tree(&formatted), "格式化改变了脚本含义:\n{}", formatted);
+        assert_eq!(parser.format(&formatted, locale), formatted, "再次格式化结果不同");
+        formatted
+    }
+
+    #[test]
+    fn format_preserves_meaning() {
+        let formatted = assert_round_trip(SCRIPT, None);
+        assert!(formatted.starts_with("# 登录用例\n用例: TC001\n脚本名: 登录成功\n标签: 冒烟, 登录, 回归\n"));
+        assert!(formatted.contains("\n    点击 [{登录按钮}]\n"));
+        assert!(formatted.contains("\n        定向滑动 [{500,1500}, 上, 800]\n"));
+        assert!(!formatted.contains("\n\n\n"));
+    }
+
+    #[test]
+    fn format_keeps_inline_details_on_its_line() {
+        let formatted = assert_round_trip("用例: TC001\n详情: 注意: 需要登录\n步骤:\n    返回\n", None);
+        assert!(formatted.contains("详情: 注意: 需要登录\n"));
+        let details = ScriptParser::new().parse(&formatted).unwrap().details;
+        assert_eq!(details.get("描述").map(String::as_str), Some("注意: 需要登录"));
+        assert!(!details.contains_key("注意"));
+    }
+
+    #[test]
+    fn locale_conversion_preserves_meaning() {
+        let english = assert_round_trip(SCRIPT, Some(Locale::En));
+        assert!(english.contains("\nsteps:\n"));
+        assert!(english.contains("directional_swipe [{500,1500}, up, 800]"));
+        // 文本参数中的关键字不转换
+        assert!(english.contains("input [{备注}, 可见]"));
+        assert!(english.contains("set [方向, up]"));
+
+        let chinese = assert_round_trip(&english, Some(Locale::Zh));
+        assert_eq!(tree(&chinese), tree(SCRIPT));
+    }
+}
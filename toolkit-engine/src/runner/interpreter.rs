@@ -1,49 +1,574 @@
 // ScriptInterpreter模块 - 脚本解释器，将TKS指令转换为可执行的ADB指令
 
-use crate::{Result, TkeError, TksStep, TksCommand, TksParam, Point, Controller, Recognizer};
-use std::path::PathBuf;
-use tracing::{debug, info, error};
+use super::keywords::KeywordTable;
+use super::shell::ShellAllowList;
+use crate::controller::gesture;
+use crate::{Result, TkeError, TksStep, TksCommand, TksParam, AttributeCondition, Point, Bounds, UIElement, RelativeLocator, Anchor, Controller, Recognizer, StepResult, Locale};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn, error};
 
 pub struct ScriptInterpreter {
     project_path: PathBuf,
     device_id: Option<String>,
     controller: Controller,
     recognizer: Recognizer,
+    // 读取命令使用的OCR配置: 设置了URL则使用在线OCR，否则使用离线OCR语言
+    ocr_url: Option<String>,
+    ocr_lang: String,
+    // 脚本变量（${变量名} 插值）
+    variables: HashMap<String, String>,
+    // 当前步骤用到或设置的变量，用于记录到 StepResult
+    step_variables: HashMap<String, String>,
+    // 当前步骤的 超时= 修饰符（等待元素的最长时间）
+    step_timeout: Option<Duration>,
+    // 截图和失败现场的保存目录（本次执行的结果目录）；未设置时截图保存到 workarea，失败时不保存现场
+    artifact_dir: Option<PathBuf>,
+    // 当前步骤保存的截图，用于记录到 StepResult
+    step_screenshot: Option<String>,
+    // 关键字表（含项目别名），用于解析变量插值得到的方向和状态
+    keywords: KeywordTable,
 }
 
+// 读取命令的结果会保存到该变量中
+pub const READ_RESULT_VARIABLE: &str = "读取结果";
+
+// 执行命令 的标准输出和退出码会保存到这两个变量中
+pub const SHELL_OUTPUT_VARIABLE: &str = "命令输出";
+pub const SHELL_EXIT_CODE_VARIABLE: &str = "退出码";
+
+// 以该前缀开头的环境变量会作为脚本变量预置（去掉前缀）
+const ENV_VARIABLE_PREFIX: &str = "TKE_VAR_";
+
+// 等待元素或界面稳定的默认最长时间
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 等待元素或界面稳定时刷新UI状态的默认间隔范围（超时时间的 1/10，限制在该范围内）
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// 等待界面稳定：默认需要连续相同的UI树次数
+const DEFAULT_IDLE_DUMPS: i32 = 3;
+
+// 滚动查找：默认最多滑动次数、每次滑动的时长、滑动后等待列表停止的时间
+const DEFAULT_SCROLL_SWIPES: usize = 20;
+const SCROLL_SWIPE_DURATION: u32 = 500;
+const SCROLL_SETTLE: Duration = Duration::from_millis(500);
+
+// 重试=N 的步骤两次执行之间的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
 impl ScriptInterpreter {
     pub fn new(project_path: PathBuf, device_id: Option<String>) -> Result<Self> {
         let controller = Controller::new(device_id.clone())?;
         let recognizer = Recognizer::new(project_path.clone())?;
+
+        let ocr_url = std::env::var("TKE_OCR_URL").ok().filter(|url| !url.is_empty());
+        let ocr_lang = std::env::var("TKE_OCR_LANG").unwrap_or_else(|_| "eng".to_string());
+
+        // 从环境变量预置脚本变量
+        let variables = std::env::vars()
+            .filter_map(|(key, value)| {
+                key.strip_prefix(ENV_VARIABLE_PREFIX).map(|name| (name.to_string(), value))
+            })
+            .collect();
+
+        // 与解析器使用同一份关键字表，变量中的方向和状态也能使用项目别名
+        let mut keywords = KeywordTable::builtin();
+        if let Err(e) = keywords.load_project(&project_path) {
+            warn!("加载关键字别名失败，使用内置关键字: {}", e);
+            keywords = KeywordTable::builtin();
+        }
         
         Ok(Self {
             project_path,
             device_id,
             controller,
             recognizer,
+            ocr_url,
+            ocr_lang,
+            variables,
+            step_variables: HashMap::new(),
+            step_timeout: None,
+            artifact_dir: None,
+            step_screenshot: None,
+            keywords,
         })
     }
+
+    // 设置脚本变量（覆盖同名变量）
+    pub fn set_variables(&mut self, variables: &HashMap<String, String>) {
+        for (name, value) in variables {
+            self.variables.insert(name.clone(), value.clone());
+        }
+    }
+
+    // 设置截图和失败现场的保存目录
+    pub fn set_artifact_dir(&mut self, dir: Option<PathBuf>) {
+        self.artifact_dir = dir;
+    }
+
+    // 获取当前所有脚本变量
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+
+    // 获取上一个步骤用到或设置的变量
+    pub fn step_variables(&self) -> &HashMap<String, String> {
+        &self.step_variables
+    }
     
-    // 解释并执行单个步骤
+    // 执行单个步骤并生成执行结果（块命令会递归执行子步骤）
+    // 步骤带 重试=N 时失败后最多再执行 N 次，每次失败的错误记录在 retry_errors 中
+    pub async fn run_step(&mut self, index: usize, step: &TksStep) -> StepResult {
+        let start_time = Instant::now();
+        let mut retry_errors = Vec::new();
+
+        let mut result = loop {
+            let result = self.run_step_once(index, step).await;
+            // 所在块已超时，不再重试
+            if result.success || retry_errors.len() as u32 >= step.modifiers.retry || self.deadline_passed() {
+                break result;
+            }
+
+            let error = result.error.unwrap_or_default();
+            info!("步骤 '{}' 第 {} 次执行失败，准备重试: {}", step.raw, retry_errors.len() + 1, error);
+            retry_errors.push(error);
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        };
+
+        result.attempts = retry_errors.len() as u32 + 1;
+        result.retry_errors = retry_errors;
+        result.allowed_failure = !result.success && step.modifiers.allow_failure;
+
+        // 失败时保存现场截图和UI树（块命令的失败子步骤已经保存过）
+        let child_failed = result.children.last().is_some_and(|child| !child.success);
+        if !result.success && !child_failed {
+            self.capture_failure(step, &mut result).await;
+        }
+
+        result.duration_ms = start_time.elapsed().as_millis() as u64;
+
+        result
+    }
+
+    // 所在块的截止时间是否已过
+    fn deadline_passed(&self) -> bool {
+        self.controller.deadline().is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // 执行一次步骤；带 超时= 的步骤超过时限即失败（等待元素时作为等待的最长时间）
+    async fn run_step_once(&mut self, index: usize, step: &TksStep) -> StepResult {
+        let start_time = Instant::now();
+        let mut result = StepResult {
+            index,
+            command: step.raw.clone(),
+            line: step.line_number,
+            file: None,
+            success: true,
+            error: None,
+            duration_ms: 0,
+            variables: HashMap::new(),
+            branch: None,
+            children: Vec::new(),
+            iteration: None,
+            attempts: 1,
+            retry_errors: Vec::new(),
+            allowed_failure: false,
+            screenshot: None,
+            ui_xml: None,
+        };
+
+        let timeout = step.modifiers.timeout_ms.map(|ms| Duration::from_millis(ms as u64));
+
+        // 步骤的截止时间不晚于所在块的截止时间（等待 的超时是轮询时长，不作为截止时间）
+        // 设备命令是同步执行的，外层的 tokio 超时无法打断它们；控制器在到期时终止正在执行的 adb 命令
+        let outer_deadline = self.controller.deadline();
+        let own_deadline = timeout.filter(|_| step.command != TksCommand::Wait).map(|timeout| start_time + timeout);
+        let deadline = match (outer_deadline, own_deadline) {
+            (Some(outer), Some(own)) => Some(outer.min(own)),
+            (outer, own) => outer.or(own),
+        };
+        self.controller.set_deadline(deadline);
+
+        let has_children = matches!(step.command, TksCommand::If | TksCommand::Repeat | TksCommand::RepeatUntil | TksCommand::Call);
+        let execution = async {
+            match step.command {
+                TksCommand::If => self.run_if_block(step, &mut result).await,
+                TksCommand::Repeat => self.run_repeat_block(step, &mut result).await,
+                TksCommand::RepeatUntil => self.run_repeat_until_block(step, &mut result).await,
+                TksCommand::Call => self.run_call_block(step, &mut result).await,
+                _ => {
+                    self.step_timeout = timeout;
+                    let outcome = self.interpret_step(step).await.map_err(|e| e.to_string());
+                    self.step_timeout = None;
+                    result.variables = self.step_variables.clone();
+                    result.screenshot = self.step_screenshot.take();
+                    outcome
+                }
+            }
+        };
+
+        // 单个命令到期即中断；块命令不整体中断，子步骤继承截止时间，到期后不再执行剩余子步骤，已执行的子步骤结果保留
+        let outcome = match deadline {
+            Some(deadline) if !has_children => {
+                match tokio::time::timeout_at(deadline.into(), execution).await {
+                    Ok(outcome) => outcome,
+                    Err(_) => {
+                        self.step_timeout = None;
+                        self.step_screenshot = None;
+                        Err(String::new())
+                    }
+                }
+            }
+            _ => execution.await,
+        };
+        self.controller.set_deadline(outer_deadline);
+
+        // 到期后失败的步骤按超时报告（被终止的设备命令、未执行完的块）
+        let outcome = match outcome {
+            Err(_) if own_deadline.is_some_and(|own| Instant::now() >= own) => {
+                Err(format!("步骤执行超时（{}ms）", timeout.unwrap_or_default().as_millis()))
+            }
+            Err(_) if outer_deadline.is_some_and(|outer| Instant::now() >= outer) => {
+                Err("所在块执行超时，步骤被中断".to_string())
+            }
+            outcome => outcome,
+        };
+
+        if let Err(error) = outcome {
+            result.success = false;
+            result.error = Some(error);
+        }
+        result.duration_ms = start_time.elapsed().as_millis() as u64;
+
+        result
+    }
+
+    // 保存失败现场：截图和UI树保存到结果目录，文件名为 line<行号>_failed.png/.xml
+    async fn capture_failure(&mut self, step: &TksStep, result: &mut StepResult) {
+        let Some(ref dir) = self.artifact_dir else {
+            return;
+        };
+
+        let screenshot = Self::artifact_path(dir, &format!("line{}_failed", step.line_number), "png");
+        let ui_xml = screenshot.with_extension("xml");
+        // 超时失败时截止时间已过，保存现场不受所在块的截止时间限制
+        let deadline = self.controller.deadline();
+        self.controller.set_deadline(None);
+        let capture = async {
+            tokio::fs::create_dir_all(dir).await.map_err(TkeError::IoError)?;
+            self.controller.capture_screenshot(&screenshot).await?;
+            self.controller.capture_ui_tree(&ui_xml).await
+        };
+
+        let captured = capture.await;
+        self.controller.set_deadline(deadline);
+        match captured {
+            Ok(()) => {
+                info!("已保存第{}行的失败现场: {}", step.line_number, screenshot.display());
+                result.screenshot = Some(screenshot.to_string_lossy().to_string());
+                result.ui_xml = Some(ui_xml.to_string_lossy().to_string());
+            }
+            Err(e) => warn!("保存第{}行的失败现场失败: {}", step.line_number, e),
+        }
+    }
+
+    // 结果目录中不重名的文件路径（重名时在文件名后加 _2、_3 ...）
+    fn artifact_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+        let stem = super::Runner::sanitize_file_name(stem);
+        let mut path = dir.join(format!("{}.{}", stem, extension));
+        let mut index = 2;
+        while path.exists() {
+            path = dir.join(format!("{}_{}.{}", stem, index, extension));
+            index += 1;
+        }
+        path
+    }
+
+    // 依次执行子步骤，遇到失败立即停止并返回失败原因
+    async fn run_steps(&mut self, steps: &[TksStep], results: &mut Vec<StepResult>) -> std::result::Result<(), String> {
+        for (index, step) in steps.iter().enumerate() {
+            // 块命令超过截止时间后不再执行剩余子步骤
+            if self.deadline_passed() {
+                return Err(format!("执行超时，第{}行及之后的步骤未执行", step.line_number));
+            }
+
+            let step_result = Box::pin(self.run_step(index, step)).await;
+            let error = step_result.error.clone();
+            // 允许失败 的步骤失败时继续执行
+            let success = step_result.success || step_result.allowed_failure;
+            results.push(step_result);
+
+            if !success {
+                return Err(error.unwrap_or_else(|| format!("第{}行执行失败", step.line_number)));
+            }
+
+            // 步骤间短暂延迟
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+
+        Ok(())
+    }
+
+    // 执行 如果 块：条件成立执行主体，否则执行 否则 分支
+    async fn run_if_block(&mut self, step: &TksStep, result: &mut StepResult) -> std::result::Result<(), String> {
+        self.step_variables.clear();
+        let resolved = self.resolve_step(step).map_err(|e| e.to_string())?;
+        let condition = self.evaluate_condition(&resolved.params, resolved.expected.as_deref()).await
+            .map_err(|e| e.to_string())?;
+        result.variables = self.step_variables.clone();
+
+        info!("条件 '{}' {}", step.raw, if condition { "成立" } else { "不成立" });
+
+        let body = if condition {
+            result.branch = Some("如果".to_string());
+            &step.body
+        } else if !step.else_body.is_empty() {
+            result.branch = Some("否则".to_string());
+            &step.else_body
+        } else {
+            return Ok(());
+        };
+
+        self.run_steps(body, &mut result.children).await
+    }
+
+    // 执行 重复 块：固定次数执行循环体
+    async fn run_repeat_block(&mut self, step: &TksStep, result: &mut StepResult) -> std::result::Result<(), String> {
+        self.step_variables.clear();
+        let resolved = self.resolve_step(step).map_err(|e| e.to_string())?;
+        result.variables = self.step_variables.clone();
+
+        let times = resolved.params.first()
+            .ok_or_else(|| "重复命令需要次数".to_string())
+            .and_then(|p| self.extract_number(p).map_err(|e| e.to_string()))?;
+
+        for iteration in 0..times.max(0) as usize {
+            debug!("重复第 {}/{} 次", iteration + 1, times);
+            self.run_iteration(&step.body, iteration, &mut result.children).await?;
+        }
+
+        Ok(())
+    }
+
+    // 执行 重复直到 块：每次迭代前检查条件，成立则结束，超过最大次数仍不成立则失败
+    async fn run_repeat_until_block(&mut self, step: &TksStep, result: &mut StepResult) -> std::result::Result<(), String> {
+        self.step_variables.clear();
+        let resolved = self.resolve_step(step).map_err(|e| e.to_string())?;
+        result.variables = self.step_variables.clone();
+
+        // 最后一个参数为最大次数，其余为条件
+        let (max_param, condition) = resolved.params.split_last()
+            .ok_or_else(|| "重复直到命令需要条件和最大次数".to_string())?;
+        let max_times = self.extract_number(max_param).map_err(|e| e.to_string())?.max(0) as usize;
+
+        for iteration in 0..=max_times {
+            let satisfied = self.evaluate_condition(condition, resolved.expected.as_deref()).await
+                .map_err(|e| e.to_string())?;
+            if satisfied {
+                info!("条件 '{}' 在第 {} 次检查时成立", step.raw, iteration + 1);
+                return Ok(());
+            }
+
+            if iteration == max_times {
+                break;
+            }
+
+            debug!("重复直到第 {}/{} 次", iteration + 1, max_times);
+            self.run_iteration(&step.body, iteration, &mut result.children).await?;
+        }
+
+        Err(format!("重复 {} 次后条件仍未满足: {}", max_times, step.raw))
+    }
+
+    // 执行 调用：绑定参数为变量后在当前会话中执行子脚本步骤
+    // - 位置参数绑定为 参数1、参数2 ...
+    // - 命名参数 名称=值 绑定为同名变量
+    async fn run_call_block(&mut self, step: &TksStep, result: &mut StepResult) -> std::result::Result<(), String> {
+        self.step_variables.clear();
+        let resolved = self.resolve_step(step).map_err(|e| e.to_string())?;
+
+        let mut bindings = Vec::new();
+        for (position, param) in resolved.params.iter().skip(1).enumerate() {
+            let value = Self::param_to_string(param).map_err(|e| e.to_string())?;
+            match value.split_once('=') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    bindings.push((name.trim().to_string(), value.trim().to_string()));
+                }
+                _ => bindings.push((format!("参数{}", position + 1), value)),
+            }
+        }
+
+        // 绑定参数，记录原值以便调用结束后恢复
+        let mut previous = Vec::new();
+        for (name, value) in bindings {
+            previous.push((name.clone(), self.variables.insert(name.clone(), value.clone())));
+            self.step_variables.insert(name, value);
+        }
+        result.variables = self.step_variables.clone();
+
+        let callee = step.callee.as_ref().map(|p| p.to_string_lossy().to_string());
+        info!("调用子脚本: {}", callee.as_deref().unwrap_or("未知"));
+
+        let outcome = self.run_steps(&step.body, &mut result.children).await;
+
+        for (name, value) in previous {
+            match value {
+                Some(value) => self.variables.insert(name, value),
+                None => self.variables.remove(&name),
+            };
+        }
+
+        // 子步骤归属于被调用的脚本文件（更深层的调用已经标记了自己的文件）
+        for child in result.children.iter_mut().filter(|c| c.file.is_none()) {
+            child.file = callee.clone();
+        }
+
+        outcome
+    }
+
+    // 执行一次循环迭代，子步骤结果标记迭代序号
+    async fn run_iteration(&mut self, steps: &[TksStep], iteration: usize, results: &mut Vec<StepResult>) -> std::result::Result<(), String> {
+        let start = results.len();
+        let outcome = self.run_steps(steps, results).await;
+        for step_result in results[start..].iter_mut() {
+            step_result.iteration = Some(iteration);
+        }
+        outcome
+    }
+
+    // 解释并执行单个步骤（不含块命令）
     pub async fn interpret_step(&mut self, step: &TksStep) -> Result<()> {
         debug!("执行步骤: {} (行号: {})", step.raw, step.line_number);
+
+        // 执行时解析变量插值
+        self.step_variables.clear();
+        let step = &self.resolve_step(step)?;
         
         match step.command {
             TksCommand::Launch => self.execute_launch(&step.params).await,
             TksCommand::Close => self.execute_close(&step.params),
+            TksCommand::Install => self.execute_install(&step.params),
+            TksCommand::Uninstall => self.execute_uninstall(&step.params),
+            TksCommand::ClearData => self.execute_clear_data(&step.params),
+            TksCommand::Grant => self.execute_permission(&step.params, true),
+            TksCommand::Revoke => self.execute_permission(&step.params, false),
             TksCommand::Click => self.execute_click(&step.params).await,
             TksCommand::Press => self.execute_press(&step.params).await,
             TksCommand::Swipe => self.execute_swipe(&step.params).await,
             TksCommand::DirectionalSwipe => self.execute_directional_swipe(&step.params).await,
+            TksCommand::Drag => self.execute_drag(&step.params).await,
+            TksCommand::DirectionalDrag => self.execute_directional_drag(&step.params).await,
+            TksCommand::ScrollFind => self.execute_scroll_find(&step.params).await,
+            TksCommand::PinchIn => self.execute_pinch(&step.params, false).await,
+            TksCommand::PinchOut => self.execute_pinch(&step.params, true).await,
+            TksCommand::Rotate => self.execute_rotate(&step.params).await,
+            TksCommand::MultiSwipe => self.execute_multi_swipe(&step.params).await,
             TksCommand::Input => self.execute_input(&step.params).await,
             TksCommand::Clear => self.execute_clear(&step.params).await,
             TksCommand::HideKeyboard => self.execute_hide_keyboard(),
             TksCommand::Back => self.execute_back(),
+            TksCommand::Key => self.execute_key(&step.params),
+            TksCommand::Screenshot => self.execute_screenshot(&step.params, step.line_number).await,
+            TksCommand::Shell => self.execute_shell(&step.params),
             TksCommand::Wait => self.execute_wait(&step.params).await,
             TksCommand::Assert => self.execute_assert(&step.params).await,
+            TksCommand::Read => self.execute_read(&step.params, step.expected.as_deref()).await,
+            TksCommand::SetVariable => self.execute_set_variable(&step.params),
+            TksCommand::If | TksCommand::Repeat | TksCommand::RepeatUntil | TksCommand::Call => Err(TkeError::ScriptExecuteError(
+                "块命令需要通过 run_step 执行".to_string()
+            )),
         }
     }
     
+    // 替换步骤参数中的 ${变量名}
+    fn resolve_step(&mut self, step: &TksStep) -> Result<TksStep> {
+        let mut resolved = step.clone();
+
+        for param in resolved.params.iter_mut() {
+            match param {
+                TksParam::Text(text) => *text = self.interpolate(text)?,
+                TksParam::XmlElement { name, relative, .. } => {
+                    *name = self.interpolate(name)?;
+                    if let Some(RelativeLocator { anchor: Anchor::Element(anchor) | Anchor::Text(anchor), .. }) = relative {
+                        *anchor = self.interpolate(anchor)?;
+                    }
+                }
+                TksParam::ImageElement(name) => *name = self.interpolate(name)?,
+                TksParam::Attribute(condition) => {
+                    if let Some(value) = condition.value_mut() {
+                        *value = self.interpolate(value)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(ref expected) = step.expected {
+            resolved.expected = Some(self.interpolate(expected)?);
+        }
+
+        Ok(resolved)
+    }
+
+    // 字符串插值，未定义的变量视为错误
+    fn interpolate(&mut self, text: &str) -> Result<String> {
+        if !text.contains("${") {
+            return Ok(text.to_string());
+        }
+
+        let re = Regex::new(r"\$\{([^}]+)\}")
+            .map_err(|e| TkeError::ScriptExecuteError(format!("变量正则错误: {}", e)))?;
+
+        let mut result = String::new();
+        let mut last = 0;
+        for caps in re.captures_iter(text) {
+            let whole = caps.get(0).unwrap();
+            let name = caps[1].trim();
+            let value = self.variables.get(name)
+                .ok_or_else(|| TkeError::ScriptExecuteError(format!("未定义的变量: {}", name)))?
+                .clone();
+
+            result.push_str(&text[last..whole.start()]);
+            result.push_str(&value);
+            last = whole.end();
+            self.step_variables.insert(name.to_string(), value);
+        }
+        result.push_str(&text[last..]);
+
+        Ok(result)
+    }
+
+    // 设置变量
+    fn execute_set_variable(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.len() < 2 {
+            return Err(TkeError::InvalidArgument("设置命令需要变量名和值".to_string()));
+        }
+
+        let name = self.extract_text(&params[0])?;
+        let value = Self::param_to_string(&params[1])?;
+
+        info!("设置变量: {} = {}", name, value);
+        self.variables.insert(name.clone(), value.clone());
+        self.step_variables.insert(name, value);
+        Ok(())
+    }
+
+    // 将参数转换为变量值
+    fn param_to_string(param: &TksParam) -> Result<String> {
+        match param {
+            TksParam::Text(t) => Ok(t.clone()),
+            TksParam::Number(n) => Ok(n.to_string()),
+            TksParam::Duration(ms) => Ok(ms.to_string()),
+            TksParam::Boolean(b) => Ok(b.to_string()),
+            TksParam::Direction(d) => Ok(d.clone()),
+            _ => Err(TkeError::InvalidArgument("变量值必须是文本或数字".to_string())),
+        }
+    }
+
     // 启动应用
     async fn execute_launch(&mut self, params: &[TksParam]) -> Result<()> {
         if params.len() < 2 {
@@ -74,6 +599,67 @@ impl ScriptInterpreter {
         self.controller.stop_app(&package)
     }
     
+    // 安装应用: 安装 [APK路径, 选项?]，相对路径相对于项目目录，选项为 -r / -g
+    fn execute_install(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("安装命令需要APK路径".to_string()));
+        }
+
+        let apk = PathBuf::from(self.extract_text(&params[0])?);
+        let apk = if apk.is_absolute() { apk } else { self.project_path.join(apk) };
+        let options = match params.get(1) {
+            Some(param) => self.extract_text(param)?,
+            None => String::new(),
+        };
+        let (mut replace, mut grant_permissions) = (false, false);
+        for option in options.split_whitespace() {
+            match option {
+                "-r" => replace = true,
+                "-g" => grant_permissions = true,
+                _ => return Err(TkeError::InvalidArgument(format!("未知的安装选项 '{}'，只支持 -r 和 -g", option))),
+            }
+        }
+
+        self.controller.install_app(&apk, replace, grant_permissions)
+    }
+
+    // 卸载应用
+    fn execute_uninstall(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("卸载命令需要包名".to_string()));
+        }
+
+        let package = self.extract_text(&params[0])?;
+        self.controller.uninstall_app(&package, false)
+    }
+
+    // 清除应用数据
+    fn execute_clear_data(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("清除数据命令需要包名".to_string()));
+        }
+
+        let package = self.extract_text(&params[0])?;
+        self.controller.clear_app_data(&package)
+    }
+
+    // 授予/撤销运行时权限: 授予权限 [包名, 权限]，权限可以简写为 CAMERA
+    fn execute_permission(&mut self, params: &[TksParam], grant: bool) -> Result<()> {
+        if params.len() < 2 {
+            return Err(TkeError::InvalidArgument("权限命令需要包名和权限".to_string()));
+        }
+
+        let package = self.extract_text(&params[0])?;
+        let permission = self.extract_text(&params[1])?;
+        let permission = if grant {
+            self.controller.grant_permission(&package, &permission)?
+        } else {
+            self.controller.revoke_permission(&package, &permission)?
+        };
+        debug!("{}权限 {}: {}", if grant { "授予" } else { "撤销" }, package, permission);
+        Ok(())
+    }
+
     // 点击操作
     async fn execute_click(&mut self, params: &[TksParam]) -> Result<()> {
         if params.is_empty() {
@@ -142,7 +728,236 @@ impl ScriptInterpreter {
         
         self.controller.swipe(from_point.x, from_point.y, to_point.x, to_point.y, duration)
     }
-    
+
+    // 拖动操作
+    async fn execute_drag(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.len() < 2 {
+            return Err(TkeError::InvalidArgument("拖动命令需要目标元素和终点坐标".to_string()));
+        }
+
+        let from_point = self.resolve_target(&params[0]).await?;
+        let to_point = self.resolve_target(&params[1]).await?;
+        let duration = if params.len() > 2 {
+            self.extract_duration(&params[2])?
+        } else {
+            1000 // 默认1000ms
+        };
+
+        self.controller.drag(from_point.x, from_point.y, to_point.x, to_point.y, duration)
+    }
+
+    // 定向拖动操作
+    async fn execute_directional_drag(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.len() < 3 {
+            return Err(TkeError::InvalidArgument("定向拖动命令需要目标元素、方向和距离".to_string()));
+        }
+
+        let from_point = self.resolve_target(&params[0]).await?;
+        let direction = self.extract_direction(&params[1])?;
+        let distance = self.extract_number(&params[2])?;
+        let duration = if params.len() > 3 {
+            self.extract_duration(&params[3])?
+        } else {
+            1000 // 默认1000ms
+        };
+
+        let to_point = match direction.as_str() {
+            "up" => Point::new(from_point.x, from_point.y - distance),
+            "down" => Point::new(from_point.x, from_point.y + distance),
+            "left" => Point::new(from_point.x - distance, from_point.y),
+            "right" => Point::new(from_point.x + distance, from_point.y),
+            _ => return Err(TkeError::InvalidArgument(format!("无效的方向: {}", direction))),
+        };
+
+        self.controller.drag(from_point.x, from_point.y, to_point.x, to_point.y, duration)
+    }
+
+    // 双指缩小/放大: 以目标中心为中心缩放，距离默认为元素宽度的 3/5
+    async fn execute_pinch(&mut self, params: &[TksParam], zoom_in: bool) -> Result<()> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("双指缩放命令需要目标".to_string()));
+        }
+
+        let (center, bounds) = self.resolve_gesture_target(&params[0]).await?;
+        let distance = match params.get(1) {
+            Some(param) => self.extract_number(param)?,
+            None => bounds.map_or(gesture::DEFAULT_PINCH_DISTANCE, |b| b.width() * 3 / 5),
+        };
+        let duration = match params.get(2) {
+            Some(param) => self.extract_duration(param)?,
+            None => 500, // 默认500ms
+        };
+
+        if zoom_in {
+            self.controller.pinch_out(center.x, center.y, distance, duration)
+        } else {
+            self.controller.pinch_in(center.x, center.y, distance, duration)
+        }
+    }
+
+    // 双指旋转: 两指绕目标中心转动指定角度（正数为顺时针），半径为元素短边的 1/4
+    async fn execute_rotate(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.len() < 2 {
+            return Err(TkeError::InvalidArgument("双指旋转命令需要目标和角度".to_string()));
+        }
+
+        let (center, bounds) = self.resolve_gesture_target(&params[0]).await?;
+        let degrees = self.extract_number(&params[1])?;
+        let duration = match params.get(2) {
+            Some(param) => self.extract_duration(param)?,
+            None => 500, // 默认500ms
+        };
+        let radius = bounds.map_or(gesture::DEFAULT_ROTATE_RADIUS, |b| {
+            (b.width().min(b.height()) / 4).max(gesture::MIN_FINGER_RADIUS)
+        });
+
+        self.controller.rotate(center.x, center.y, radius, degrees, duration)
+    }
+
+    // 多指滑动: 与 定向滑动 相同，但由多个手指并排滑动（默认2指）
+    async fn execute_multi_swipe(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.len() < 3 {
+            return Err(TkeError::InvalidArgument("多指滑动命令需要起点、方向和距离".to_string()));
+        }
+
+        let from_point = self.resolve_target(&params[0]).await?;
+        let direction = self.extract_direction(&params[1])?;
+        let distance = self.extract_number(&params[2])?;
+        let fingers = match params.get(3) {
+            Some(param) => self.extract_number(param)?.max(0) as usize,
+            None => 2,
+        };
+        let duration = match params.get(4) {
+            Some(param) => self.extract_duration(param)?,
+            None => 300, // 默认300ms
+        };
+
+        let to_point = match direction.as_str() {
+            "up" => Point::new(from_point.x, from_point.y - distance),
+            "down" => Point::new(from_point.x, from_point.y + distance),
+            "left" => Point::new(from_point.x - distance, from_point.y),
+            "right" => Point::new(from_point.x + distance, from_point.y),
+            _ => return Err(TkeError::InvalidArgument(format!("无效的方向: {}", direction))),
+        };
+
+        self.controller.multi_finger_swipe(from_point.x, from_point.y, to_point.x, to_point.y, fingers, duration)
+    }
+
+    // 解析手势目标: XML元素和文本返回中心点和元素范围，坐标和图像元素只有中心点
+    async fn resolve_gesture_target(&mut self, param: &TksParam) -> Result<(Point, Option<Bounds>)> {
+        match param {
+            TksParam::XmlElement { .. } | TksParam::Text(_) => {
+                self.controller.capture_ui_state(&self.project_path).await?;
+                let element = self.find_element(param)?;
+                Ok((element.center(), Some(element.bounds)))
+            }
+            _ => Ok((self.resolve_target(param).await?, None)),
+        }
+    }
+
+    // 滚动查找：在容器中朝指定方向滚动，直到目标可见
+    // - 方向为列表滚动的方向，默认 下（手指向上滑动）
+    // - 未指定容器时使用界面中面积最大的可滚动元素
+    // - 滑动后容器内的元素与滑动前完全相同，视为已到达列表末尾
+    async fn execute_scroll_find(&mut self, params: &[TksParam]) -> Result<()> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("滚动查找命令需要目标".to_string()));
+        }
+
+        let target = &params[0];
+        let direction = match params.get(1) {
+            Some(param) => self.extract_direction(param)?,
+            None => "down".to_string(),
+        };
+        let max_swipes = match params.get(3) {
+            Some(param) => self.extract_number(param)?.max(0) as usize,
+            None => DEFAULT_SCROLL_SWIPES,
+        };
+
+        self.controller.capture_ui_state(&self.project_path).await?;
+        let container = match params.get(2) {
+            Some(TksParam::XmlElement { name, strategy, relative }) => {
+                self.recognizer.find_xml_ui_element(name, strategy.as_deref(), relative.as_ref())?
+            }
+            Some(_) => return Err(TkeError::InvalidArgument("滚动查找的容器必须是XML元素".to_string())),
+            None => self.recognizer.find_scrollable_container()?,
+        };
+        let (from, to) = Self::scroll_gesture(&container.bounds, &direction)?;
+        let name = Self::target_name(target);
+
+        let mut previous = self.container_fingerprint(&container.bounds)?;
+        for swipes in 0..=max_swipes {
+            if self.is_target_visible(target)? {
+                info!("滚动查找: 滑动 {} 次后找到 '{}'", swipes, name);
+                return Ok(());
+            }
+            if swipes == max_swipes {
+                break;
+            }
+
+            self.controller.swipe(from.x, from.y, to.x, to.y, SCROLL_SWIPE_DURATION)?;
+            tokio::time::sleep(SCROLL_SETTLE).await;
+            self.controller.capture_ui_state(&self.project_path).await?;
+
+            let current = self.container_fingerprint(&container.bounds)?;
+            if current == previous {
+                if self.is_target_visible(target)? {
+                    return Ok(());
+                }
+                return Err(TkeError::ScriptExecuteError(format!(
+                    "滚动查找失败: 已滚动到列表末尾（滑动 {} 次），仍未找到 '{}'", swipes + 1, name
+                )));
+            }
+            previous = current;
+        }
+
+        Err(TkeError::ScriptExecuteError(format!(
+            "滚动查找失败: 滑动 {} 次后仍未找到 '{}'", max_swipes, name
+        )))
+    }
+
+    // 在容器内滚动的手势（起点, 终点）：沿滚动方向的反方向滑动容器尺寸的一半
+    fn scroll_gesture(container: &Bounds, direction: &str) -> Result<(Point, Point)> {
+        let center = container.center();
+        let (near_x, far_x) = (container.x1 + container.width() / 4, container.x1 + container.width() * 3 / 4);
+        let (near_y, far_y) = (container.y1 + container.height() / 4, container.y1 + container.height() * 3 / 4);
+
+        match direction {
+            "down" => Ok((Point::new(center.x, far_y), Point::new(center.x, near_y))),
+            "up" => Ok((Point::new(center.x, near_y), Point::new(center.x, far_y))),
+            "right" => Ok((Point::new(far_x, center.y), Point::new(near_x, center.y))),
+            "left" => Ok((Point::new(near_x, center.y), Point::new(far_x, center.y))),
+            _ => Err(TkeError::InvalidArgument(format!("无效的方向: {}", direction))),
+        }
+    }
+
+    // 容器内元素的摘要（用于判断滑动后列表是否还有变化）
+    fn container_fingerprint(&self, container: &Bounds) -> Result<Vec<String>> {
+        Ok(self.recognizer.elements_within(container)?
+            .iter()
+            .map(|e| format!(
+                "{}|{}|{}|{}|{},{},{},{}",
+                e.class_name,
+                e.text.as_deref().unwrap_or_default(),
+                e.content_desc.as_deref().unwrap_or_default(),
+                e.resource_id.as_deref().unwrap_or_default(),
+                e.bounds.x1, e.bounds.y1, e.bounds.x2, e.bounds.y2
+            ))
+            .collect())
+    }
+
+    // 目标当前是否可见（调用前需刷新UI状态）
+    fn is_target_visible(&self, target: &TksParam) -> Result<bool> {
+        match target {
+            TksParam::XmlElement { name, strategy, relative } => {
+                self.recognizer.is_xml_element_visible(name, strategy.as_deref(), relative.as_ref())
+            }
+            TksParam::ImageElement(name) => Ok(self.recognizer.find_image_element(name).is_ok()),
+            TksParam::Text(text) => Ok(self.recognizer.find_ui_element_by_text(text).is_ok()),
+            _ => Err(TkeError::InvalidArgument("滚动查找的目标必须是元素或文本".to_string())),
+        }
+    }
+
     // 输入文本
     async fn execute_input(&mut self, params: &[TksParam]) -> Result<()> {
         if params.len() < 2 {
@@ -184,7 +999,91 @@ impl ScriptInterpreter {
         self.controller.back()
     }
     
+    // 按键操作: 按键 [名称或键码, 长按?]
+    fn execute_key(&mut self, params: &[TksParam]) -> Result<()> {
+        let key = match params.first() {
+            Some(TksParam::Number(code)) => code.to_string(),
+            Some(param) => self.extract_text(param)?,
+            None => return Err(TkeError::InvalidArgument("按键命令需要按键名称或键码".to_string())),
+        };
+        let long_press = match params.get(1) {
+            None => false,
+            Some(TksParam::Text(t)) if KeywordTable::is_long_press(t) => true,
+            Some(_) => return Err(TkeError::InvalidArgument("按键命令的第二个参数只能是 长按".to_string())),
+        };
+
+        let codes = self.controller.press_key(&key, long_press)?;
+        debug!("按键 {} -> {:?}{}", key, codes, if long_press { " (长按)" } else { "" });
+        Ok(())
+    }
+    
+    // 截图操作: 截图 [名称?]，保存为 <名称>.png，未指定名称时为 line<行号>_screenshot.png
+    async fn execute_screenshot(&mut self, params: &[TksParam], line_number: usize) -> Result<()> {
+        let name = match params.first() {
+            Some(param) => Self::param_to_string(param)?,
+            None => format!("line{}_screenshot", line_number),
+        };
+        let dir = self.artifact_dir.clone().unwrap_or_else(|| self.project_path.join("workarea"));
+        std::fs::create_dir_all(&dir)
+            .map_err(TkeError::IoError)?;
+
+        let path = Self::artifact_path(&dir, &name, "png");
+        self.controller.capture_screenshot(&path).await?;
+        info!("截图已保存: {}", path.display());
+        self.step_screenshot = Some(path.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    // 执行设备命令: 执行命令 [命令, 输出正则?]
+    // 标准输出（去掉首尾空白）和退出码保存到变量中，退出码非0或输出不匹配正则时步骤失败
+    fn execute_shell(&mut self, params: &[TksParam]) -> Result<()> {
+        let command = match params.first() {
+            Some(param) => self.extract_text(param)?,
+            None => return Err(TkeError::InvalidArgument("执行命令需要命令内容".to_string())),
+        };
+        let pattern = params.get(1).map(|p| self.extract_text(p)).transpose()?;
+
+        // 每次执行时重新加载允许列表，修改后无需重启
+        ShellAllowList::load_project(&self.project_path)?
+            .check(&command)
+            .map_err(TkeError::InvalidArgument)?;
+
+        let output = self.controller.shell(&command)?;
+        let stdout = output.stdout.trim().to_string();
+        info!("执行命令 '{}' 退出码 {}: {}", command, output.exit_code, stdout);
+
+        for (name, value) in [
+            (SHELL_OUTPUT_VARIABLE, stdout.clone()),
+            (SHELL_EXIT_CODE_VARIABLE, output.exit_code.to_string()),
+        ] {
+            self.variables.insert(name.to_string(), value.clone());
+            self.step_variables.insert(name.to_string(), value);
+        }
+
+        if output.exit_code != 0 {
+            let detail = if output.stderr.trim().is_empty() { &stdout } else { output.stderr.trim() };
+            return Err(TkeError::ScriptExecuteError(
+                format!("命令 '{}' 执行失败（退出码 {}）: {}", command, output.exit_code, detail)
+            ));
+        }
+
+        if let Some(pattern) = pattern {
+            let regex = Regex::new(&pattern)
+                .map_err(|e| TkeError::InvalidArgument(format!("无效的正则表达式 '{}': {}", pattern, e)))?;
+            if !regex.is_match(&stdout) {
+                return Err(TkeError::ScriptExecuteError(
+                    format!("命令输出断言失败: 输出 '{}' 不匹配 '{}'", stdout, pattern)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     // 等待操作
+    // - [时长]: 固定等待
+    // - [{元素}, 存在/不存在/可见/不可见?, 超时?, 间隔?]: 等待元素达到该状态（默认 存在）
+    // - [界面稳定, 次数?, 超时?, 间隔?]: 等待连续 N 次获取的UI树完全相同（默认3次）
     async fn execute_wait(&mut self, params: &[TksParam]) -> Result<()> {
         if params.is_empty() {
             // 默认等待1秒
@@ -207,59 +1106,26 @@ impl ScriptInterpreter {
                     tokio::time::sleep(tokio::time::Duration::from_millis(*num as u64)).await;
                 }
             }
-            TksParam::XmlElement { name, strategy } => {
-                debug!("等待XML元素出现: {}, 策略: {:?}", name, strategy);
-                // 等待元素出现，最多等待30秒
-                let timeout = tokio::time::Duration::from_secs(30);
-                let start = tokio::time::Instant::now();
-
-                while start.elapsed() < timeout {
-                    // 刷新UI状态
-                    if let Err(e) = self.controller.capture_ui_state(&self.project_path).await {
-                        debug!("刷新UI状态失败: {}", e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                        continue;
-                    }
-
-                    // 尝试查找元素
-                    if self.recognizer.find_xml_element(name, strategy.as_deref()).is_ok() {
-                        info!("找到XML元素: {}", name);
-                        return Ok(()); // 找到元素，结束等待
-                    } else {
-                        debug!("未找到XML元素: {}", name);
-                    }
-
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                }
-
-                return Err(TkeError::ScriptExecuteError(format!("等待XML元素超时: {}", name)));
+            target @ (TksParam::XmlElement { .. } | TksParam::ImageElement(_)) => {
+                // 第二个参数是状态时，超时和间隔从第三个参数开始
+                let (state, timing) = match params.get(1) {
+                    Some(TksParam::Boolean(exists)) => ((false, *exists), &params[2..]),
+                    Some(TksParam::Visibility(visible)) => ((true, *visible), &params[2..]),
+                    _ => ((false, true), &params[1..]),
+                };
+                let (timeout, interval) = self.wait_timing(timing)?;
+                return self.wait_for_element(target, state, timeout, interval).await;
             }
-            TksParam::ImageElement(name) => {
-                debug!("等待图像元素出现: {}", name);
-                // 等待元素出现，最多等待30秒
-                let timeout = tokio::time::Duration::from_secs(30);
-                let start = tokio::time::Instant::now();
-
-                while start.elapsed() < timeout {
-                    // 刷新UI状态
-                    if let Err(e) = self.controller.capture_ui_state(&self.project_path).await {
-                        debug!("刷新UI状态失败: {}", e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                        continue;
-                    }
-
-                    // 尝试查找元素
-                    if self.recognizer.find_image_element(name).is_ok() {
-                        info!("找到图像元素: {}", name);
-                        return Ok(()); // 找到元素，结束等待
-                    } else {
-                        debug!("未找到图像元素: {}", name);
-                    }
-
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            TksParam::Text(text) if KeywordTable::is_ui_idle(text) => {
+                let (dumps, timing) = match params.get(1) {
+                    Some(TksParam::Number(count)) => (*count, &params[2..]),
+                    _ => (DEFAULT_IDLE_DUMPS, &params[1..]),
+                };
+                if dumps < 2 {
+                    return Err(TkeError::InvalidArgument("界面稳定的连续次数至少为2".to_string()));
                 }
-
-                return Err(TkeError::ScriptExecuteError(format!("等待图像元素超时: {}", name)));
+                let (timeout, interval) = self.wait_timing(timing)?;
+                return self.wait_for_ui_idle(dumps as usize, timeout, interval).await;
             }
             TksParam::Text(text) => {
                 // 支持文本参数的等待，与JS版本保持一致
@@ -277,49 +1143,390 @@ impl ScriptInterpreter {
         
         Ok(())
     }
-    
+
+    // 解析等待的 [超时?, 间隔?]：超时默认取 超时= 修饰符（再默认30秒），间隔默认为超时的 1/10
+    fn wait_timing(&self, params: &[TksParam]) -> Result<(Duration, Duration)> {
+        let timeout = match params.first() {
+            Some(param) => Self::wait_duration(param)?,
+            None => self.step_timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT),
+        };
+        let interval = match params.get(1) {
+            Some(param) => Self::wait_duration(param)?,
+            None => (timeout / 10).clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL),
+        };
+        Ok((timeout, interval))
+    }
+
+    // 等待参数中的时长：与固定等待相同，不大于3600的数字当作秒数，否则当作毫秒数
+    fn wait_duration(param: &TksParam) -> Result<Duration> {
+        match param {
+            TksParam::Duration(ms) => Ok(Duration::from_millis(*ms as u64)),
+            TksParam::Number(num) if *num <= 3600 => Ok(Duration::from_secs(*num as u64)),
+            TksParam::Number(num) => Ok(Duration::from_millis(*num as u64)),
+            _ => Err(TkeError::InvalidArgument("等待的超时和间隔必须是时长（如 10s、500ms）".to_string())),
+        }
+    }
+
+    // 等待元素达到指定状态: state 为 (是否检查可见性, 期望值)
+    async fn wait_for_element(&mut self, target: &TksParam, state: (bool, bool), timeout: Duration, interval: Duration) -> Result<()> {
+        let (kind, name) = match target {
+            TksParam::XmlElement { name, .. } => ("XML元素", name),
+            TksParam::ImageElement(name) => ("图像元素", name),
+            _ => return Err(TkeError::InvalidArgument("等待目标必须是元素".to_string())),
+        };
+        let (check_visibility, expected) = state;
+        // 等待出现时沿用原来的提示，其他状态在提示中带上状态关键字
+        let state_name = match state {
+            (false, true) => "",
+            (false, false) => "不存在",
+            (true, true) => "可见",
+            (true, false) => "不可见",
+        };
+        debug!("等待{}{}: {}，最多 {}ms，间隔 {}ms", kind, if state_name.is_empty() { "出现" } else { state_name },
+            name, timeout.as_millis(), interval.as_millis());
+
+        let start = tokio::time::Instant::now();
+        loop {
+            // 刷新UI状态后检查元素状态
+            match self.controller.capture_ui_state(&self.project_path).await {
+                Ok(()) if self.check_element_state(target, check_visibility)? == expected => {
+                    info!("{}已{}: {}", kind, if state_name.is_empty() { "出现" } else { state_name }, name);
+                    return Ok(());
+                }
+                Ok(()) => debug!("{}尚未{}: {}", kind, if state_name.is_empty() { "出现" } else { state_name }, name),
+                Err(e) => debug!("刷新UI状态失败: {}", e),
+            }
+
+            if start.elapsed() + interval > timeout {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+
+        Err(TkeError::ScriptExecuteError(format!(
+            "等待{}{}超时（{}ms）: {}", kind, state_name, timeout.as_millis(), name
+        )))
+    }
+
+    // 等待界面稳定: 连续 dumps 次获取的UI树内容完全相同（获取失败视为未稳定）
+    async fn wait_for_ui_idle(&mut self, dumps: usize, timeout: Duration, interval: Duration) -> Result<()> {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        debug!("等待界面稳定: 连续 {} 次相同，最多 {}ms，间隔 {}ms", dumps, timeout.as_millis(), interval.as_millis());
+
+        let start = tokio::time::Instant::now();
+        let mut last_hash = None;
+        let mut same_count = 0;
+        loop {
+            match self.controller.get_ui_xml().await {
+                Ok(xml) => {
+                    let mut hasher = DefaultHasher::new();
+                    xml.hash(&mut hasher);
+                    let hash = hasher.finish();
+                    same_count = if last_hash == Some(hash) { same_count + 1 } else { 1 };
+                    last_hash = Some(hash);
+                    if same_count >= dumps {
+                        info!("界面已稳定（{}ms）", start.elapsed().as_millis());
+                        return Ok(());
+                    }
+                }
+                Err(e) => {
+                    debug!("获取UI树失败: {}", e);
+                    last_hash = None;
+                    same_count = 0;
+                }
+            }
+
+            if start.elapsed() + interval > timeout {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+
+        Err(TkeError::ScriptExecuteError(format!("等待界面稳定超时（{}ms）", timeout.as_millis())))
+    }
+
     // 断言操作
     async fn execute_assert(&mut self, params: &[TksParam]) -> Result<()> {
         if params.len() < 2 {
             return Err(TkeError::InvalidArgument("断言命令需要目标和条件".to_string()));
         }
-        
-        // 刷新UI状态
-        self.controller.capture_ui_state(&self.project_path).await?;
 
-        let element_exists = match &params[0] {
-            TksParam::XmlElement { name, strategy } => {
-                self.recognizer.find_xml_element(name, strategy.as_deref()).is_ok()
-            }
-            TksParam::ImageElement(name) => {
-                self.recognizer.find_image_element(name).is_ok()
+        // 属性条件：比较匹配到的UI元素的属性
+        if let TksParam::Attribute(condition) = &params[1] {
+            self.controller.capture_ui_state(&self.project_path).await?;
+            let element = self.find_element(&params[0])?;
+            let (passed, actual) = Self::check_attribute(&element, condition)?;
+            if !passed {
+                return Err(TkeError::ScriptExecuteError(format!(
+                    "断言失败: 元素 '{}' 期望{}，实际{}",
+                    Self::target_name(&params[0]), Self::describe_attribute(condition), actual
+                )));
             }
-            _ => return Err(TkeError::InvalidArgument("断言目标必须是元素".to_string())),
-        };
+            return Ok(());
+        }
 
-        let expected = match &params[1] {
-            TksParam::Boolean(b) => *b,
-            TksParam::Text(t) => t == "存在",
-            _ => return Err(TkeError::InvalidArgument("断言条件无效".to_string())),
-        };
+        let (check_visibility, expected) = self.parse_element_state(&params[1])?;
 
-        if element_exists != expected {
-            let element_name = match &params[0] {
-                TksParam::XmlElement { name, .. } | TksParam::ImageElement(name) => name,
-                _ => "未知元素",
+        // 刷新UI状态
+        self.controller.capture_ui_state(&self.project_path).await?;
+
+        let actual = self.check_element_state(&params[0], check_visibility)?;
+
+        if actual != expected {
+            let element_name = Self::target_name(&params[0]);
+            let describe = |state: bool| match (check_visibility, state) {
+                (false, true) => "存在",
+                (false, false) => "不存在",
+                (true, true) => "可见",
+                (true, false) => "不可见",
             };
 
             return Err(TkeError::ScriptExecuteError(
                 format!("断言失败: 元素 '{}' {}，但期望{}",
                        element_name,
-                       if element_exists { "存在" } else { "不存在" },
-                       if expected { "存在" } else { "不存在" })
+                       describe(actual),
+                       describe(expected))
             ));
         }
         
         Ok(())
     }
+
+    // 检查UI元素是否满足属性条件，返回 (是否满足, 实际值的描述)
+    fn check_attribute(element: &UIElement, condition: &AttributeCondition) -> Result<(bool, String)> {
+        let text = element.text.as_deref().unwrap_or_default();
+        let flag = |actual: bool, expected: bool, state: AttributeCondition| {
+            (actual == expected, KeywordTable::attribute_keyword(&state, Locale::Zh).to_string())
+        };
+
+        let result = match condition {
+            AttributeCondition::Checked(expected) => {
+                flag(element.checked, *expected, AttributeCondition::Checked(element.checked))
+            }
+            AttributeCondition::Enabled(expected) => {
+                flag(element.enabled, *expected, AttributeCondition::Enabled(element.enabled))
+            }
+            AttributeCondition::Selected(expected) => {
+                flag(element.selected, *expected, AttributeCondition::Selected(element.selected))
+            }
+            AttributeCondition::Focused(expected) => {
+                flag(element.focused, *expected, AttributeCondition::Focused(element.focused))
+            }
+            AttributeCondition::TextEquals(expected) => (text.trim() == expected.trim(), format!("文本为 '{}'", text)),
+            AttributeCondition::TextContains(expected) => (text.contains(expected.as_str()), format!("文本为 '{}'", text)),
+            AttributeCondition::TextMatches(pattern) => {
+                let re = Regex::new(pattern)
+                    .map_err(|_| TkeError::InvalidArgument(format!("无效的正则表达式: {}", pattern)))?;
+                (re.is_match(text), format!("文本为 '{}'", text))
+            }
+        };
+
+        Ok(result)
+    }
+
+    // 属性条件的描述（如 已勾选、文本包含 '登录'）
+    fn describe_attribute(condition: &AttributeCondition) -> String {
+        let keyword = KeywordTable::attribute_keyword(condition, Locale::Zh);
+        match condition.value() {
+            Some(value) => format!("{} '{}'", keyword, value),
+            None => keyword.to_string(),
+        }
+    }
+
+    // 目标元素的名称（用于错误信息）
+    fn target_name(target: &TksParam) -> &str {
+        match target {
+            TksParam::XmlElement { name, .. } | TksParam::ImageElement(name) | TksParam::Text(name) => name,
+            _ => "未知元素",
+        }
+    }
+
+    // 解析元素状态条件，返回 (是否检查可见性, 期望结果)
+    // 变量插值得到的文本按关键字表解析（含英文关键字和项目别名）
+    fn parse_element_state(&self, param: &TksParam) -> Result<(bool, bool)> {
+        match param {
+            TksParam::Boolean(b) => Ok((false, *b)),
+            TksParam::Visibility(v) => Ok((true, *v)),
+            TksParam::Text(t) => self.keywords.state(t.trim())
+                .ok_or_else(|| TkeError::InvalidArgument(format!("条件无效: {}", t))),
+            _ => Err(TkeError::InvalidArgument("条件无效".to_string())),
+        }
+    }
+
+    // 检查元素当前是否存在或可见（调用前需刷新UI状态）
+    fn check_element_state(&self, target: &TksParam, check_visibility: bool) -> Result<bool> {
+        match target {
+            TksParam::XmlElement { name, strategy, relative } => {
+                if check_visibility {
+                    self.recognizer.is_xml_element_visible(name, strategy.as_deref(), relative.as_ref())
+                } else {
+                    Ok(self.recognizer.find_xml_element(name, strategy.as_deref(), relative.as_ref()).is_ok())
+                }
+            }
+            TksParam::ImageElement(name) => {
+                // 对于图片识别来说，存在和可见是等效的
+                Ok(self.recognizer.find_image_element(name).is_ok())
+            }
+            _ => Err(TkeError::InvalidArgument("条件目标必须是元素".to_string())),
+        }
+    }
+
+    // 计算块命令的条件是否成立
+    // - [{元素}, 存在/不存在/可见/不可见]: 元素状态条件
+    // - [{元素}] == 期望文本: 文本条件（与 读取 相同的识别方式）
+    async fn evaluate_condition(&mut self, params: &[TksParam], expected: Option<&str>) -> Result<bool> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("条件需要目标元素".to_string()));
+        }
+
+        if let Some(expected) = expected {
+            return match self.read_text(params).await {
+                Ok((actual, element_text)) => Ok(Self::read_matches(&actual, element_text.as_deref(), expected)),
+                Err(TkeError::ElementNotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            };
+        }
+
+        if params.len() < 2 {
+            return Err(TkeError::InvalidArgument(
+                "条件需要 存在/不存在/可见/不可见 或 == 期望文本".to_string()
+            ));
+        }
+
+        // 刷新UI状态
+        self.controller.capture_ui_state(&self.project_path).await?;
+
+        // 属性条件：元素不存在时视为不成立
+        if let TksParam::Attribute(condition) = &params[1] {
+            return match self.find_element(&params[0]) {
+                Ok(element) => Ok(Self::check_attribute(&element, condition)?.0),
+                Err(TkeError::ElementNotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            };
+        }
+
+        let (check_visibility, expected) = self.parse_element_state(&params[1])?;
+        Ok(self.check_element_state(&params[0], check_visibility)? == expected)
+    }
     
+    // 读取文本（OCR识别，可选与期望文本比较）
+    async fn execute_read(&mut self, params: &[TksParam], expected: Option<&str>) -> Result<()> {
+        let (actual, element_text) = self.read_text(params).await?;
+
+        info!("读取到文本: {}", actual);
+        self.variables.insert(READ_RESULT_VARIABLE.to_string(), actual.clone());
+        self.step_variables.insert(READ_RESULT_VARIABLE.to_string(), actual.clone());
+
+        if let Some(expected) = expected {
+            if !Self::read_matches(&actual, element_text.as_deref(), expected) {
+                return Err(TkeError::ScriptExecuteError(
+                    format!("读取断言失败: 实际文本 '{}'，期望 '{}'", actual, expected)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // 识别目标区域的文本，返回 (识别结果, XML元素的 text 属性)
+    async fn read_text(&mut self, params: &[TksParam]) -> Result<(String, Option<String>)> {
+        if params.is_empty() {
+            return Err(TkeError::InvalidArgument("读取命令需要坐标或XML元素".to_string()));
+        }
+
+        // 刷新UI状态
+        self.controller.capture_ui_state(&self.project_path).await?;
+
+        // 确定识别区域，XML元素同时提供 text 属性作为后备
+        let (region, element_text) = match &params[0] {
+            TksParam::Coordinate(center) => {
+                if params.len() < 3 {
+                    return Err(TkeError::InvalidArgument("读取坐标区域需要宽度和高度".to_string()));
+                }
+                let width = self.extract_number(&params[1])?;
+                let height = self.extract_number(&params[2])?;
+                let region = Bounds::new(
+                    center.x - width / 2,
+                    center.y - height / 2,
+                    center.x + width / 2,
+                    center.y + height / 2,
+                );
+                (region, None)
+            }
+            TksParam::XmlElement { name, strategy, relative } => {
+                let element = self.recognizer.find_xml_ui_element(name, strategy.as_deref(), relative.as_ref())?;
+                (element.bounds.clone(), element.text.clone())
+            }
+            _ => return Err(TkeError::InvalidArgument("读取目标必须是坐标或XML元素".to_string())),
+        };
+
+        let ocr_text = match self.ocr_region(&region).await {
+            Ok(text) => text,
+            Err(e) => {
+                debug!("OCR识别失败: {}", e);
+                String::new()
+            }
+        };
+
+        // OCR 未识别出文字时使用元素的 text 属性
+        let element_text = element_text.filter(|t| !t.trim().is_empty());
+        let actual = if ocr_text.is_empty() {
+            element_text.clone().unwrap_or_default()
+        } else {
+            ocr_text
+        };
+
+        Ok((actual, element_text))
+    }
+
+    // 识别结果或元素 text 属性任一与期望文本一致即视为匹配
+    fn read_matches(actual: &str, element_text: Option<&str>, expected: &str) -> bool {
+        Self::text_matches(actual, expected) ||
+            element_text.is_some_and(|t| Self::text_matches(t, expected))
+    }
+
+    // 裁剪当前截图的指定区域并进行OCR识别
+    async fn ocr_region(&self, region: &Bounds) -> Result<String> {
+        let screenshot_path = self.project_path.join("workarea").join("current_screenshot.png");
+        let screenshot = image::open(&screenshot_path)
+            .map_err(|e| TkeError::ImageError(format!("读取截图失败: {}", e)))?;
+
+        // 将区域限制在截图范围内
+        let x1 = region.x1.clamp(0, screenshot.width() as i32);
+        let y1 = region.y1.clamp(0, screenshot.height() as i32);
+        let x2 = region.x2.clamp(0, screenshot.width() as i32);
+        let y2 = region.y2.clamp(0, screenshot.height() as i32);
+        if x2 <= x1 || y2 <= y1 {
+            return Err(TkeError::InvalidArgument("读取区域超出屏幕范围".to_string()));
+        }
+
+        let cropped = screenshot.crop_imm(x1 as u32, y1 as u32, (x2 - x1) as u32, (y2 - y1) as u32);
+        let mut image_data = std::io::Cursor::new(Vec::new());
+        cropped.write_to(&mut image_data, image::ImageFormat::Png)
+            .map_err(|e| TkeError::ImageError(format!("编码裁剪图片失败: {}", e)))?;
+
+        let result = match self.ocr_url {
+            Some(ref url) => crate::ocr(image_data.get_ref(), true, url).await,
+            None => crate::ocr(image_data.get_ref(), false, &self.ocr_lang).await,
+        }.map_err(|e| TkeError::OcrError(e.to_string()))?;
+
+        let text = result.texts.iter()
+            .map(|t| t.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(text)
+    }
+
+    // 文本比较，忽略空白差异（OCR 结果的空格并不稳定）
+    fn text_matches(actual: &str, expected: &str) -> bool {
+        let normalize = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        normalize(actual) == normalize(expected)
+    }
+
     // 辅助方法：解析目标位置
     async fn resolve_target(&mut self, param: &TksParam) -> Result<Point> {
         match param {
@@ -327,25 +1534,6 @@ impl ScriptInterpreter {
                 debug!("使用坐标: ({}, {})", point.x, point.y);
                 Ok(*point)
             }
-            TksParam::XmlElement { name, strategy } => {
-                debug!("查找XML元素: {}, 策略: {:?}", name, strategy);
-                // 刷新UI状态
-                if let Err(e) = self.controller.capture_ui_state(&self.project_path).await {
-                    error!("刷新UI状态失败: {}", e);
-                    return Err(e);
-                }
-
-                match self.recognizer.find_xml_element(name, strategy.as_deref()) {
-                    Ok(point) => {
-                        info!("找到XML元素 '{}' 位置: ({}, {})", name, point.x, point.y);
-                        Ok(point)
-                    }
-                    Err(e) => {
-                        error!("查找XML元素 '{}' 失败: {}", name, e);
-                        Err(e)
-                    }
-                }
-            }
             TksParam::ImageElement(name) => {
                 debug!("查找图像元素: {}", name);
                 // 刷新UI状态  
@@ -365,24 +1553,14 @@ impl ScriptInterpreter {
                     }
                 }
             }
-            TksParam::Text(text) => {
-                debug!("查找文本元素: {}", text);
+            TksParam::XmlElement { .. } | TksParam::Text(_) => {
                 // 刷新UI状态
                 if let Err(e) = self.controller.capture_ui_state(&self.project_path).await {
                     error!("刷新UI状态失败: {}", e);
                     return Err(e);
                 }
-                
-                match self.recognizer.find_element_by_text(text) {
-                    Ok(point) => {
-                        info!("找到文本元素 '{}' 位置: ({}, {})", text, point.x, point.y);
-                        Ok(point)
-                    }
-                    Err(e) => {
-                        error!("查找文本元素 '{}' 失败: {}", text, e);
-                        Err(e)
-                    }
-                }
+
+                Ok(self.find_element(param)?.center())
             }
             _ => {
                 error!("无效的目标类型: {:?}", param);
@@ -391,6 +1569,39 @@ impl ScriptInterpreter {
         }
     }
     
+    // 辅助方法：在当前UI状态中查找目标对应的UI元素（XML元素或文本，调用前需刷新UI状态）
+    fn find_element(&self, param: &TksParam) -> Result<UIElement> {
+        let (kind, name, found) = match param {
+            TksParam::XmlElement { name, strategy, relative } => {
+                debug!("查找XML元素: {}, 策略: {:?}", name, strategy);
+                ("XML元素", name, self.recognizer.find_xml_ui_element(name, strategy.as_deref(), relative.as_ref()))
+            }
+            TksParam::Text(text) => {
+                debug!("查找文本元素: {}", text);
+                ("文本元素", text, self.recognizer.find_ui_element_by_text(text))
+            }
+            TksParam::ImageElement(_) => {
+                return Err(TkeError::InvalidArgument("图片元素不支持属性条件，请使用XML元素".to_string()));
+            }
+            _ => {
+                error!("无效的目标类型: {:?}", param);
+                return Err(TkeError::InvalidArgument("无效的目标类型".to_string()));
+            }
+        };
+
+        match found {
+            Ok(element) => {
+                let point = element.center();
+                info!("找到{} '{}' 位置: ({}, {})", kind, name, point.x, point.y);
+                Ok(element)
+            }
+            Err(e) => {
+                error!("查找{} '{}' 失败: {}", kind, name, e);
+                Err(e)
+            }
+        }
+    }
+
     // 提取文本参数
     fn extract_text(&self, param: &TksParam) -> Result<String> {
         match param {
@@ -403,6 +1614,9 @@ impl ScriptInterpreter {
     fn extract_number(&self, param: &TksParam) -> Result<i32> {
         match param {
             TksParam::Number(n) => Ok(*n),
+            // 变量插值后的数字以文本形式出现
+            TksParam::Text(t) => t.trim().parse::<i32>()
+                .map_err(|_| TkeError::InvalidArgument(format!("期望数字参数: {}", t))),
             _ => Err(TkeError::InvalidArgument("期望数字参数".to_string())),
         }
     }
@@ -412,15 +1626,19 @@ impl ScriptInterpreter {
         match param {
             TksParam::Duration(ms) => Ok(*ms),
             TksParam::Number(n) => Ok(*n as u32),
+            TksParam::Text(t) => t.trim().parse::<u32>()
+                .map_err(|_| TkeError::InvalidArgument(format!("期望持续时间参数: {}", t))),
             _ => Err(TkeError::InvalidArgument("期望持续时间参数".to_string())),
         }
     }
     
-    // 提取方向参数
+    // 提取方向参数（变量插值得到的文本按关键字表解析，含英文关键字和项目别名）
     fn extract_direction(&self, param: &TksParam) -> Result<String> {
         match param {
             TksParam::Direction(d) => Ok(d.clone()),
-            TksParam::Text(t) => Ok(t.clone()),
+            TksParam::Text(t) => self.keywords.direction(t.trim())
+                .map(str::to_string)
+                .ok_or_else(|| TkeError::InvalidArgument(format!("期望方向参数（上/下/左/右）: {}", t))),
             _ => Err(TkeError::InvalidArgument("期望方向参数".to_string())),
         }
     }
@@ -0,0 +1,478 @@
+// 关键字表 - 脚本中命令、方向、状态、属性条件、块结构、头部字段和步骤修饰符的中英文关键字
+// 项目目录下的 keywords.json 可以为这些关键字添加别名
+
+use crate::{Result, TkeError, TksCommand, AttributeCondition, Relation, Locale};
+use crate::models::COMMAND_KEYWORDS;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+// 项目级关键字别名文件（相对项目目录）
+pub const KEYWORDS_FILE: &str = "keywords.json";
+
+// 块结构关键字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKeyword {
+    Else,   // 否则
+    End,    // 结束
+}
+
+// 脚本头部字段和步骤标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    CaseId,      // 用例
+    ScriptName,  // 脚本名
+    Tags,        // 标签
+    Priority,    // 优先级
+    Details,     // 详情
+    Data,        // 数据（数据驱动的参数表）
+    Setup,       // 前置（步骤之前执行）
+    Steps,       // 步骤
+    Teardown,    // 后置（无论步骤是否失败都会执行）
+}
+
+// 步骤修饰符（写在步骤行末尾）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Retry,          // 重试=次数
+    Timeout,        // 超时=时长
+    AllowFailure,   // 允许失败
+}
+
+impl Modifier {
+    // 是否需要 =值
+    pub fn takes_value(&self) -> bool {
+        !matches!(self, Modifier::AllowFailure)
+    }
+}
+
+impl Section {
+    // 是否为包含步骤的段落
+    pub fn has_steps(&self) -> bool {
+        matches!(self, Section::Setup | Section::Steps | Section::Teardown)
+    }
+}
+
+// 方向关键字: (方向值, 中文, 英文)
+const DIRECTION_KEYWORDS: [(&str, &str, &str); 4] = [
+    ("up", "上", "up"),
+    ("down", "下", "down"),
+    ("left", "左", "left"),
+    ("right", "右", "right"),
+];
+
+// 元素状态关键字: ((是否检查可见性, 期望结果), 中文, 英文)
+const STATE_KEYWORDS: [((bool, bool), &str, &str); 4] = [
+    ((false, true), "存在", "exists"),
+    ((false, false), "不存在", "not_exists"),
+    ((true, true), "可见", "visible"),
+    ((true, false), "不可见", "invisible"),
+];
+
+// 元素属性条件关键字: (条件, 中文, 英文)；文本条件写作 关键字=值
+const ATTRIBUTE_KEYWORDS: [(AttributeCondition, &str, &str); 11] = [
+    (AttributeCondition::Checked(true), "已勾选", "checked"),
+    (AttributeCondition::Checked(false), "未勾选", "unchecked"),
+    (AttributeCondition::Enabled(true), "可用", "enabled"),
+    (AttributeCondition::Enabled(false), "不可用", "disabled"),
+    (AttributeCondition::Selected(true), "已选中", "selected"),
+    (AttributeCondition::Selected(false), "未选中", "unselected"),
+    (AttributeCondition::Focused(true), "已聚焦", "focused"),
+    (AttributeCondition::Focused(false), "未聚焦", "unfocused"),
+    (AttributeCondition::TextEquals(String::new()), "文本等于", "text_equals"),
+    (AttributeCondition::TextContains(String::new()), "文本包含", "text_contains"),
+    (AttributeCondition::TextMatches(String::new()), "文本匹配", "text_matches"),
+];
+
+// 块结构关键字: (关键字, 中文, 英文)
+const BLOCK_KEYWORDS: [(BlockKeyword, &str, &str); 2] = [
+    (BlockKeyword::Else, "否则", "else"),
+    (BlockKeyword::End, "结束", "end"),
+];
+
+// 头部字段关键字: (字段, 中文, 英文)
+const SECTION_KEYWORDS: [(Section, &str, &str); 9] = [
+    (Section::CaseId, "用例", "case"),
+    (Section::ScriptName, "脚本名", "script"),
+    (Section::Tags, "标签", "tags"),
+    (Section::Priority, "优先级", "priority"),
+    (Section::Details, "详情", "details"),
+    (Section::Data, "数据", "data"),
+    (Section::Setup, "前置", "setup"),
+    (Section::Steps, "步骤", "steps"),
+    (Section::Teardown, "后置", "teardown"),
+];
+
+// 步骤修饰符关键字: (修饰符, 中文, 英文)
+const MODIFIER_KEYWORDS: [(Modifier, &str, &str); 3] = [
+    (Modifier::Retry, "重试", "retry"),
+    (Modifier::Timeout, "超时", "timeout"),
+    (Modifier::AllowFailure, "允许失败", "allow_failure"),
+];
+
+// 次数后缀（重复 3 次 / repeat 3 times）
+const COUNT_SUFFIXES: [&str; 2] = ["次", "times"];
+
+// 按键的长按选项（按键 [POWER, 长按] / key [POWER, long_press]）
+const LONG_PRESS_KEYWORDS: [&str; 2] = ["长按", "long_press"];
+
+// 等待界面稳定（等待 [界面稳定] / wait [ui_idle]）
+const UI_IDLE_KEYWORDS: [&str; 2] = ["界面稳定", "ui_idle"];
+
+// 相对定位关系关键字: (关系, 中文, 英文)，写在元素之后（{开关} 右侧 {Wi-Fi标签}）
+const RELATION_KEYWORDS: [(Relation, &str, &str); 6] = [
+    (Relation::RightOf, "右侧", "right_of"),
+    (Relation::LeftOf, "左侧", "left_of"),
+    (Relation::Above, "上方", "above"),
+    (Relation::Below, "下方", "below"),
+    (Relation::Inside, "内部", "inside"),
+    (Relation::SameGroup, "同组", "same_group"),
+];
+
+// keywords.json 的内容：每个分类中 别名 -> 已有的中文或英文关键字
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct KeywordOverrides {
+    commands: HashMap<String, String>,
+    directions: HashMap<String, String>,
+    states: HashMap<String, String>,
+    attributes: HashMap<String, String>,
+    blocks: HashMap<String, String>,
+    sections: HashMap<String, String>,
+    modifiers: HashMap<String, String>,
+}
+
+// 关键字表（内置中英文关键字 + 项目别名）
+#[derive(Debug, Clone)]
+pub struct KeywordTable {
+    commands: HashMap<String, TksCommand>,
+    directions: HashMap<String, &'static str>,
+    states: HashMap<String, (bool, bool)>,
+    attributes: HashMap<String, AttributeCondition>,  // 文本条件的值为空
+    blocks: HashMap<String, BlockKeyword>,
+    sections: HashMap<String, Section>,
+    modifiers: HashMap<String, Modifier>,
+}
+
+impl KeywordTable {
+    // 内置关键字表
+    pub fn builtin() -> Self {
+        fn both<T: Clone, V>(table: &[(T, &str, &str)], map: impl Fn(&T) -> V) -> HashMap<String, V> {
+            table.iter()
+                .flat_map(|(value, zh, en)| [(zh.to_string(), map(value)), (en.to_string(), map(value))])
+                .collect()
+        }
+
+        Self {
+            commands: both(&COMMAND_KEYWORDS, |c| c.clone()),
+            directions: both(&DIRECTION_KEYWORDS, |d| *d),
+            states: both(&STATE_KEYWORDS, |s| *s),
+            attributes: both(&ATTRIBUTE_KEYWORDS, |a| a.clone()),
+            blocks: both(&BLOCK_KEYWORDS, |b| *b),
+            sections: both(&SECTION_KEYWORDS, |s| *s),
+            modifiers: both(&MODIFIER_KEYWORDS, |m| *m),
+        }
+    }
+
+    // 加载项目目录下的 keywords.json（文件不存在时不做任何修改）
+    pub fn load_project(&mut self, project_path: &Path) -> Result<()> {
+        let path = project_path.join(KEYWORDS_FILE);
+        if !path.is_file() {
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(TkeError::IoError)?;
+        self.load_overrides(&content)
+    }
+
+    // 添加别名，如 {"commands": {"tap": "点击"}, "directions": {"oben": "上"}}
+    pub fn load_overrides(&mut self, content: &str) -> Result<()> {
+        let overrides: KeywordOverrides = serde_json::from_str(content)?;
+
+        add_aliases(&mut self.commands, overrides.commands, "命令")?;
+        add_aliases(&mut self.directions, overrides.directions, "方向")?;
+        add_aliases(&mut self.states, overrides.states, "状态")?;
+        add_aliases(&mut self.attributes, overrides.attributes, "属性条件")?;
+        add_aliases(&mut self.blocks, overrides.blocks, "块结构")?;
+        add_aliases(&mut self.sections, overrides.sections, "头部字段")?;
+        add_aliases(&mut self.modifiers, overrides.modifiers, "修饰符")?;
+
+        Ok(())
+    }
+
+    // 根据关键字查找命令
+    pub fn command(&self, word: &str) -> Option<&TksCommand> {
+        self.commands.get(word)
+    }
+
+    // 所有命令关键字（含别名，用于拼写建议）
+    pub fn command_words(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(|k| k.as_str())
+    }
+
+    // 所有方向关键字（含别名）
+    pub fn direction_words(&self) -> impl Iterator<Item = &str> {
+        self.directions.keys().map(|k| k.as_str())
+    }
+
+    // 根据关键字查找方向，返回 up/down/left/right
+    pub fn direction(&self, word: &str) -> Option<&'static str> {
+        self.directions.get(word).copied()
+    }
+
+    // 根据关键字查找元素状态，返回 (是否检查可见性, 期望结果)
+    pub fn state(&self, word: &str) -> Option<(bool, bool)> {
+        self.states.get(word).copied()
+    }
+
+    // 解析元素属性条件（已勾选 / 文本包含=登录，值可以加引号）
+    pub fn attribute(&self, word: &str) -> Option<AttributeCondition> {
+        match word.split_once('=') {
+            Some((key, value)) => self.attributes.get(key.trim())
+                .filter(|condition| condition.value().is_some())
+                .map(|condition| condition.with_value(unquote(value.trim()))),
+            None => self.attributes.get(word)
+                .filter(|condition| condition.value().is_none())
+                .cloned(),
+        }
+    }
+
+    // 根据关键字查找块结构关键字
+    pub fn block(&self, word: &str) -> Option<BlockKeyword> {
+        self.blocks.get(word).copied()
+    }
+
+    // 根据字段名查找头部字段
+    pub fn section(&self, key: &str) -> Option<Section> {
+        self.sections.get(key).copied()
+    }
+
+    // 根据关键字查找步骤修饰符
+    pub fn modifier(&self, word: &str) -> Option<Modifier> {
+        self.modifiers.get(word).copied()
+    }
+
+    // 步骤段落的标记行（前置: / 步骤: / 后置:），返回对应的段落
+    pub fn step_section(&self, line: &str) -> Option<Section> {
+        line.strip_suffix(':')
+            .or_else(|| line.strip_suffix('：'))
+            .and_then(|key| self.section(key.trim()))
+            .filter(|section| section.has_steps())
+    }
+
+    // 去掉次数后缀（3 次 / 3 times）
+    pub fn strip_count_suffix<'a>(&self, param: &'a str) -> Option<&'a str> {
+        COUNT_SUFFIXES.iter().find_map(|suffix| param.strip_suffix(suffix))
+    }
+
+    // 根据脚本中第一个关键字判断脚本使用的语言
+    pub fn detect_locale(&self, content: &str) -> Locale {
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let key = trimmed.split([':', '：']).next().unwrap_or("").trim();
+            let word = trimmed.split(|c: char| c.is_whitespace() || c == '[').next().unwrap_or("");
+            let keyword = if self.section(key).is_some() {
+                key
+            } else if self.command(word).is_some() || self.block(word).is_some() {
+                word
+            } else {
+                continue;
+            };
+
+            return if keyword.is_ascii() { Locale::En } else { Locale::Zh };
+        }
+
+        Locale::Zh
+    }
+
+    // 方向在指定语言下的关键字
+    pub fn direction_keyword(direction: &str, locale: Locale) -> Option<&'static str> {
+        DIRECTION_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == direction)
+            .map(|entry| pick(entry, locale))
+    }
+
+    // 元素状态在指定语言下的关键字
+    pub fn state_keyword(check_visibility: bool, expected: bool, locale: Locale) -> &'static str {
+        STATE_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == (check_visibility, expected))
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 元素属性条件在指定语言下的关键字（不含文本条件的值）
+    pub fn attribute_keyword(condition: &AttributeCondition, locale: Locale) -> &'static str {
+        let blank = condition.with_value(String::new());
+        ATTRIBUTE_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == blank)
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 块结构关键字在指定语言下的写法
+    pub fn block_keyword(block: BlockKeyword, locale: Locale) -> &'static str {
+        BLOCK_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == block)
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 头部字段在指定语言下的写法
+    pub fn section_keyword(section: Section, locale: Locale) -> &'static str {
+        SECTION_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == section)
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 步骤修饰符在指定语言下的关键字
+    pub fn modifier_keyword(modifier: Modifier, locale: Locale) -> &'static str {
+        MODIFIER_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == modifier)
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 次数后缀在指定语言下的写法
+    pub fn count_suffix(locale: Locale) -> &'static str {
+        match locale {
+            Locale::Zh => COUNT_SUFFIXES[0],
+            Locale::En => COUNT_SUFFIXES[1],
+        }
+    }
+
+    // 是否为按键的长按选项
+    pub fn is_long_press(word: &str) -> bool {
+        LONG_PRESS_KEYWORDS.contains(&word)
+    }
+
+    // 长按选项在指定语言下的写法
+    pub fn long_press_keyword(locale: Locale) -> &'static str {
+        match locale {
+            Locale::Zh => LONG_PRESS_KEYWORDS[0],
+            Locale::En => LONG_PRESS_KEYWORDS[1],
+        }
+    }
+
+    // 是否为等待界面稳定的关键字
+    pub fn is_ui_idle(word: &str) -> bool {
+        UI_IDLE_KEYWORDS.contains(&word)
+    }
+
+    // 界面稳定关键字在指定语言下的写法
+    pub fn ui_idle_keyword(locale: Locale) -> &'static str {
+        match locale {
+            Locale::Zh => UI_IDLE_KEYWORDS[0],
+            Locale::En => UI_IDLE_KEYWORDS[1],
+        }
+    }
+
+    // 根据关键字查找相对定位关系
+    pub fn relation(word: &str) -> Option<Relation> {
+        RELATION_KEYWORDS.iter()
+            .find(|(_, zh, en)| *zh == word || *en == word)
+            .map(|(relation, _, _)| *relation)
+    }
+
+    // 相对定位关系在指定语言下的关键字
+    pub fn relation_keyword(relation: Relation, locale: Locale) -> &'static str {
+        RELATION_KEYWORDS.iter()
+            .find(|(value, _, _)| *value == relation)
+            .map(|entry| pick(entry, locale))
+            .unwrap_or_default()
+    }
+
+    // 指定语言下的全部相对定位关系关键字（用于编辑器补全）
+    pub fn relation_keywords(locale: Locale) -> Vec<&'static str> {
+        RELATION_KEYWORDS.iter().map(|entry| pick(entry, locale)).collect()
+    }
+
+    // 指定语言下的全部方向关键字（用于编辑器补全）
+    pub fn direction_keywords(locale: Locale) -> Vec<&'static str> {
+        DIRECTION_KEYWORDS.iter().map(|entry| pick(entry, locale)).collect()
+    }
+
+    // 指定语言下的全部状态关键字（用于编辑器补全）
+    pub fn state_keywords(locale: Locale) -> Vec<&'static str> {
+        STATE_KEYWORDS.iter().map(|entry| pick(entry, locale)).collect()
+    }
+
+    // 指定语言下的全部属性条件（用于编辑器补全，文本条件以 = 结尾）
+    pub fn attribute_labels(locale: Locale) -> Vec<String> {
+        ATTRIBUTE_KEYWORDS.iter()
+            .map(|entry| match entry.0.value() {
+                Some(_) => format!("{}=", pick(entry, locale)),
+                None => pick(entry, locale).to_string(),
+            })
+            .collect()
+    }
+
+    // 指定语言下的全部头部字段关键字（用于编辑器补全）
+    pub fn section_keywords(locale: Locale) -> Vec<&'static str> {
+        SECTION_KEYWORDS.iter().map(|entry| pick(entry, locale)).collect()
+    }
+}
+
+impl Default for KeywordTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+// 从 (值, 中文, 英文) 中取出指定语言的关键字
+fn pick<T>(entry: &(T, &'static str, &'static str), locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => entry.1,
+        Locale::En => entry.2,
+    }
+}
+
+// 去掉值两端成对的引号
+fn unquote(value: &str) -> &str {
+    let quoted = value.len() >= 2 &&
+        ((value.starts_with('"') && value.ends_with('"')) ||
+         (value.starts_with('\'') && value.ends_with('\'')));
+    if quoted { &value[1..value.len() - 1] } else { value }
+}
+
+// 按别名指向的已有关键字添加别名（别名不能覆盖含义不同的已有关键字）
+fn add_aliases<V: Clone + PartialEq>(
+    table: &mut HashMap<String, V>,
+    aliases: HashMap<String, String>,
+    category: &str,
+) -> Result<()> {
+    // 按别名排序，保证错误信息稳定
+    let mut aliases: Vec<(String, String)> = aliases.into_iter().collect();
+    aliases.sort();
+
+    for (alias, target) in aliases {
+        let alias = alias.trim().to_string();
+        if alias.is_empty() || alias.contains(|c: char| c.is_whitespace() || "[]{}:：,#".contains(c)) {
+            return Err(TkeError::InvalidArgument(
+                format!("{}: {}别名 '{}' 不能为空或包含空白、括号、冒号、逗号", KEYWORDS_FILE, category, alias)
+            ));
+        }
+
+        let value = table.get(target.trim()).cloned().ok_or_else(|| {
+            TkeError::InvalidArgument(format!("{}: 未知的{}关键字 '{}'", KEYWORDS_FILE, category, target))
+        })?;
+
+        match table.get(&alias) {
+            Some(existing) if *existing != value => {
+                return Err(TkeError::InvalidArgument(
+                    format!("{}: {}别名 '{}' 与已有关键字冲突", KEYWORDS_FILE, category, alias)
+                ));
+            }
+            _ => {
+                table.insert(alias, value);
+            }
+        }
+    }
+
+    Ok(())
+}
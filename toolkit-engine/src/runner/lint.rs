@@ -0,0 +1,226 @@
+// 项目静态检查 - 校验脚本中的元素引用与 locator/element.json 是否一致
+
+use super::diagnostic::{self, Diagnostic};
+use super::parser::{LocatorReference, ScriptParser};
+use crate::{Result, TkeError, Recognizer, Locator, LocatorType, RelativeLocator, Anchor, TksStep};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// 单个脚本的检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptLint {
+    pub script: String,               // 相对项目目录的脚本路径
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// 项目检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct LintReport {
+    pub scripts: Vec<ScriptLint>,
+    pub unused_locators: Vec<String>,  // element.json 中没有被任何脚本引用的元素
+}
+
+impl LintReport {
+    pub fn error_count(&self) -> usize {
+        self.scripts.iter()
+            .flat_map(|s| &s.diagnostics)
+            .filter(|d| d.is_error())
+            .count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.scripts.iter()
+            .flat_map(|s| &s.diagnostics)
+            .filter(|d| !d.is_error())
+            .count()
+    }
+}
+
+// 检查 cases/*/script/*.tks 以及它们调用的子脚本
+pub fn lint_project(project_path: &Path, parser: &ScriptParser) -> Result<LintReport> {
+    let recognizer = Recognizer::new(project_path.to_path_buf())?;
+    let locators = recognizer.locators();
+
+    let case_scripts = collect_case_scripts(project_path)?;
+    let case_count = case_scripts.len();
+
+    // 待检查队列：用例脚本在前，子脚本在解析用例时追加
+    let mut queue = case_scripts;
+    let mut visited = HashSet::new();
+    let mut used = HashSet::new();
+    let mut scripts = Vec::new();
+    let mut index = 0;
+
+    while index < queue.len() {
+        let script_path = queue[index].clone();
+        let is_case = index < case_count;
+        index += 1;
+
+        let canonical = script_path.canonicalize().unwrap_or_else(|_| script_path.clone());
+        if !visited.insert(canonical) {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&script_path)
+            .map_err(TkeError::IoError)?;
+
+        // 子脚本的语法问题已在调用处报告，这里只检查元素引用
+        let mut diagnostics = if is_case {
+            parser.check_file(&script_path)?
+        } else {
+            Vec::new()
+        };
+
+        for reference in parser.locator_references(&content) {
+            used.insert(reference.name);
+        }
+        diagnostics.extend(check_locator_references(project_path, locators, parser, &content));
+        diagnostics.sort_by_key(|d| (d.line, d.start));
+
+        if let Ok(script) = parser.parse_file(&script_path) {
+            collect_callees(&script.setup, &mut queue);
+            collect_callees(&script.steps, &mut queue);
+            collect_callees(&script.teardown, &mut queue);
+        }
+
+        scripts.push(ScriptLint {
+            script: script_path.strip_prefix(project_path)
+                .unwrap_or(&script_path)
+                .to_string_lossy()
+                .to_string(),
+            diagnostics,
+        });
+    }
+
+    // 被引用元素的相对定位锚点（element.json 中的 relative）也算作被引用
+    let mut pending: Vec<String> = used.iter().cloned().collect();
+    while let Some(name) = pending.pop() {
+        if let Some(RelativeLocator { anchor: Anchor::Element(anchor), .. }) = locators.get(&name).and_then(|l| l.relative.as_ref()) {
+            if used.insert(anchor.clone()) {
+                pending.push(anchor.clone());
+            }
+        }
+    }
+
+    let mut unused_locators: Vec<String> = locators.keys()
+        .filter(|name| !used.contains(*name))
+        .cloned()
+        .collect();
+    unused_locators.sort();
+
+    Ok(LintReport { scripts, unused_locators })
+}
+
+// 检查脚本内容中的元素引用是否与 locator 定义一致
+pub fn check_locator_references(
+    project_path: &Path,
+    locators: &HashMap<String, Locator>,
+    parser: &ScriptParser,
+    content: &str,
+) -> Vec<Diagnostic> {
+    parser.locator_references(content)
+        .iter()
+        .filter_map(|reference| check_reference(project_path, locators, reference))
+        .collect()
+}
+
+// 查找项目中的用例脚本 cases/*/script/*.tks
+fn collect_case_scripts(project_path: &Path) -> Result<Vec<PathBuf>> {
+    let cases_dir = project_path.join("cases");
+
+    if !cases_dir.exists() {
+        return Err(TkeError::InvalidProjectPath(
+            "项目目录中没有cases文件夹".to_string()
+        ));
+    }
+
+    let mut scripts = Vec::new();
+    for entry in std::fs::read_dir(&cases_dir).map_err(TkeError::IoError)? {
+        let script_dir = entry.map_err(TkeError::IoError)?.path().join("script");
+        if !script_dir.is_dir() {
+            continue;
+        }
+
+        for script_entry in std::fs::read_dir(&script_dir).map_err(TkeError::IoError)? {
+            let script_path = script_entry.map_err(TkeError::IoError)?.path();
+            if script_path.extension().and_then(|s| s.to_str()) == Some("tks") {
+                scripts.push(script_path);
+            }
+        }
+    }
+
+    scripts.sort();
+    Ok(scripts)
+}
+
+// 收集 调用 引用的子脚本路径
+fn collect_callees(steps: &[TksStep], callees: &mut Vec<PathBuf>) {
+    for step in steps {
+        if let Some(ref callee) = step.callee {
+            callees.push(callee.clone());
+        }
+        collect_callees(&step.body, callees);
+        collect_callees(&step.else_body, callees);
+    }
+}
+
+// 检查单个元素引用
+fn check_reference(
+    project_path: &Path,
+    locators: &HashMap<String, Locator>,
+    reference: &LocatorReference,
+) -> Option<Diagnostic> {
+    let error = |message: String| {
+        Diagnostic::error(reference.line, reference.start, reference.end, message)
+    };
+    // 保留 &策略 后缀的替换文本
+    let replacement = |name: &str, image: bool| match (&reference.strategy, image) {
+        (_, true) => format!("@{{{}}}", name),
+        (Some(strategy), false) => format!("{{{}}}&{}", name, strategy),
+        (None, false) => format!("{{{}}}", name),
+    };
+
+    let Some(locator) = locators.get(&reference.name) else {
+        let kind = if reference.image { "图片元素" } else { "元素" };
+        let mut d = error(format!("未定义的{} '{}'（locator/element.json）", kind, reference.name));
+        if let Some(candidate) = diagnostic::closest_match(&reference.name, locators.keys().map(|k| k.as_str())) {
+            d.message.push_str(&format!("，是否为 '{}'?", candidate));
+            d = d.with_suggestion(replacement(candidate, reference.image));
+        }
+        return Some(d);
+    };
+
+    match (&locator.locator_type, reference.image) {
+        (LocatorType::Image, false) => Some(
+            error(format!("'{}' 是图片元素，应写作 @{{{}}}", reference.name, reference.name))
+                .with_suggestion(replacement(&reference.name, true))
+        ),
+        (LocatorType::Xml, true) => Some(
+            error(format!("'{}' 是XML元素，应写作 {{{}}}", reference.name, reference.name))
+                .with_suggestion(replacement(&reference.name, false))
+        ),
+        (LocatorType::Image, true) => match locator.path {
+            None => Some(error(format!("图片元素 '{}' 缺少 path 字段", reference.name))),
+            Some(ref path) if !project_path.join(path).is_file() => {
+                Some(error(format!("图片元素 '{}' 的文件不存在: {}", reference.name, path)))
+            }
+            Some(_) => None,
+        },
+        (LocatorType::Xml, false) => {
+            let strategy = reference.strategy.as_deref()?;
+            if locator.strategy_value(strategy).is_some() {
+                return None;
+            }
+            let field = match strategy {
+                "resourceId" => "resource_id",
+                "className" => "class_name",
+                "contentDesc" => "content_desc",
+                other => other,
+            };
+            Some(error(format!(
+                "元素 '{}' 未定义 {} 字段，无法使用 &{} 策略", reference.name, field, strategy
+            )))
+        }
+    }
+}
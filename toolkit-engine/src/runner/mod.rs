@@ -3,34 +3,51 @@
 // 子模块
 mod parser;
 mod interpreter;
+mod diagnostic;
+mod lint;
+mod formatter;
+mod data;
+mod keywords;
+mod shell;
 
 // 导出
-pub use parser::ScriptParser;
+pub use parser::{ScriptParser, LocatorReference};
+pub use diagnostic::{Diagnostic, Severity, CommandSignature, ParamKind, signature, usage};
+pub use keywords::{KeywordTable, BlockKeyword, Section, Modifier};
+pub use lint::{LintReport, ScriptLint, check_locator_references};
 pub use interpreter::ScriptInterpreter;
+pub use shell::{ShellAllowList, SHELL_ALLOWLIST_FILE};
 
 use crate::{
-    Result, TkeError, TksScript,
+    Result, TkeError, TksScript, TksStep,
     ExecutionResult, StepResult
 };
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
 
 pub struct Runner {
     project_path: PathBuf,
     device_id: Option<String>,
     pub parser: ScriptParser,  // 为 Toolkit Studio 开放访问
     interpreter: Option<ScriptInterpreter>,
+    variables: HashMap<String, String>,  // 预置的脚本变量（如 CLI --var）
     is_running: bool,
     should_stop: bool,
 }
 
 impl Runner {
     pub fn new(project_path: PathBuf, device_id: Option<String>) -> Self {
+        let mut parser = ScriptParser::new();
+        parser.set_project_path(project_path.clone());
+
         Self {
             project_path,
             device_id,
-            parser: ScriptParser::new(),
+            parser,
             interpreter: None,
+            variables: HashMap::new(),
             is_running: false,
             should_stop: false,
         }
@@ -41,6 +58,11 @@ impl Runner {
         self.device_id = device_id;
     }
     
+    // 设置预置的脚本变量
+    pub fn set_variables(&mut self, variables: HashMap<String, String>) {
+        self.variables = variables;
+    }
+
     // 运行单行脚本指令
     pub async fn run_single_step(&mut self, line: &str) -> Result<StepResult> {
         // 构造一个最小的脚本来解析单行指令
@@ -58,27 +80,13 @@ impl Runner {
             self.project_path.clone(),
             self.device_id.clone()
         )?;
-
-        let step = &script.steps[0];
-        let start_time = Instant::now();
+        interpreter.set_variables(&self.variables);
 
         // 执行单个步骤
-        match interpreter.interpret_step(step).await {
-            Ok(()) => Ok(StepResult {
-                index: 0,
-                command: line.to_string(),
-                success: true,
-                error: None,
-                duration_ms: start_time.elapsed().as_millis() as u64,
-            }),
-            Err(e) => Ok(StepResult {
-                index: 0,
-                command: line.to_string(),
-                success: false,
-                error: Some(e.to_string()),
-                duration_ms: start_time.elapsed().as_millis() as u64,
-            })
-        }
+        let mut step_result = interpreter.run_step(0, &script.steps[0]).await;
+        step_result.command = line.to_string();
+
+        Ok(step_result)
     }
 
     // 运行脚本文件
@@ -99,94 +107,169 @@ impl Runner {
         self.run_script(script).await
     }
     
-    // 运行脚本
+    // 运行脚本；定义了 数据: 的脚本按数据表的每一行执行一次，
+    // 返回各行的汇总结果，每行的结果在 rows 中
     pub async fn run_script(&mut self, script: TksScript) -> Result<ExecutionResult> {
-        self.is_running = true;
         self.should_stop = false;
 
-        // 初始化解释器
-        let mut interpreter = ScriptInterpreter::new(
-            self.project_path.clone(),
-            self.device_id.clone()
-        )?;
-
-        let start_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
-
-        let mut result = ExecutionResult {
-            success: true,
-            case_id: script.case_id.clone(),
-            script_name: script.script_name.clone(),
-            start_time: chrono::DateTime::from_timestamp(start_time as i64 / 1000, 0)
-                .unwrap_or_default()
-                .to_rfc3339(),
-            end_time: String::new(),
-            steps: Vec::new(),
-            error: None,
+        let Some(ref data) = script.data else {
+            return self.run_script_once(&script, None).await;
         };
 
-        // 执行每个步骤
-        for (index, step) in script.steps.iter().enumerate() {
-            // 检查是否需要停止
+        let mut summary = Self::new_result(&script, None);
+        for index in 0..data.rows.len() {
             if self.should_stop {
-                result.success = false;
-                result.error = Some("执行被中止".to_string());
                 break;
             }
-
-            let step_start = Instant::now();
-            let step_result = match interpreter.interpret_step(step).await {
-                Ok(()) => StepResult {
-                    index,
-                    command: step.raw.clone(),
-                    success: true,
-                    error: None,
-                    duration_ms: step_start.elapsed().as_millis() as u64,
-                },
+            // 某一行的执行环境错误（如设备断开、结果无法保存）记录在该行的结果中，继续执行后续数据行
+            let result = match self.run_script_once(&script, Some(index)).await {
+                Ok(result) => result,
                 Err(e) => {
+                    warn!("数据行 {} 执行出错: {}", data.row_key(index), e);
+                    self.is_running = false;
+                    let mut result = Self::new_result(&script, Some(index));
                     result.success = false;
                     result.error = Some(e.to_string());
-
-                    StepResult {
-                        index,
-                        command: step.raw.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                        duration_ms: step_start.elapsed().as_millis() as u64,
-                    }
+                    result.end_time = Self::now_rfc3339();
+                    result
                 }
             };
+            summary.rows.push(result);
+        }
 
-            result.steps.push(step_result.clone());
+        // 汇总结果：所有数据行都成功时成功，错误为第一个失败行的错误
+        summary.success = summary.rows.iter().all(|r| r.success);
+        summary.error = summary.rows.iter()
+            .find(|r| !r.success)
+            .map(|r| format!("数据行 {} 失败: {}",
+                             r.data_key.as_deref().unwrap_or_default(),
+                             r.error.as_deref().unwrap_or("执行失败")));
+        summary.end_time = Self::now_rfc3339();
 
-            // 如果步骤失败，停止执行
-            if !step_result.success {
-                break;
-            }
+        Ok(summary)
+    }
 
-            // 步骤间短暂延迟
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    // 执行一次脚本，row 为数据驱动时的数据行序号
+    async fn run_script_once(&mut self, script: &TksScript, row: Option<usize>) -> Result<ExecutionResult> {
+        self.is_running = true;
+
+        let mut result = Self::new_result(script, row);
+
+        // 初始化解释器，数据行的列绑定为变量（覆盖同名的预置变量）
+        let mut interpreter = ScriptInterpreter::new(
+            self.project_path.clone(),
+            self.device_id.clone()
+        )?;
+        interpreter.set_variables(&self.variables);
+        interpreter.set_variables(&result.data);
+
+        // 截图和失败现场保存在本次执行的结果目录中
+        let (run_dir, run_name) = self.run_dir(script, row.zip(result.data_key.as_deref()));
+        interpreter.set_artifact_dir(Some(run_dir.clone()));
+
+        // 前置步骤失败时不再执行步骤
+        let mut error = self.run_steps(&mut interpreter, &script.setup, &mut result.setup, true).await
+            .map(|e| format!("前置失败: {}", e));
+        if error.is_none() {
+            error = self.run_steps(&mut interpreter, &script.steps, &mut result.steps, true).await;
         }
+        result.success = error.is_none();
+        result.error = error;
 
-        let end_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
+        // 后置步骤总是执行（即使前置或步骤失败、执行被中止），单个后置步骤失败不影响后续步骤
+        result.teardown_error = self.run_steps(&mut interpreter, &script.teardown, &mut result.teardown, false).await;
 
-        result.end_time = chrono::DateTime::from_timestamp(end_time as i64 / 1000, 0)
-            .unwrap_or_default()
-            .to_rfc3339();
+        result.end_time = Self::now_rfc3339();
 
         self.is_running = false;
 
         // 保存执行结果
-        self.save_result(&result, &script).await?;
+        self.save_result(&result, &run_dir, &run_name).await?;
 
         Ok(result)
     }
     
+    // 创建尚未执行的结果，row 为数据驱动时的数据行序号
+    fn new_result(script: &TksScript, row: Option<usize>) -> ExecutionResult {
+        let (data_key, data) = match (&script.data, row) {
+            (Some(table), Some(index)) => (Some(table.row_key(index)), table.row_variables(index)),
+            _ => (None, HashMap::new()),
+        };
+
+        ExecutionResult {
+            success: true,
+            case_id: script.case_id.clone(),
+            script_name: match data_key {
+                Some(ref key) => format!("{}[{}]", script.script_name, key),
+                None => script.script_name.clone(),
+            },
+            details: script.details.clone(),
+            tags: script.tags.clone(),
+            priority: script.priority.clone(),
+            data_key,
+            data,
+            start_time: Self::now_rfc3339(),
+            end_time: String::new(),
+            setup: Vec::new(),
+            steps: Vec::new(),
+            error: None,
+            teardown: Vec::new(),
+            teardown_error: None,
+            rows: Vec::new(),
+        }
+    }
+
+    // 当前时间（RFC 3339，精确到秒）
+    fn now_rfc3339() -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        chrono::DateTime::from_timestamp(now as i64 / 1000, 0)
+            .unwrap_or_default()
+            .to_rfc3339()
+    }
+
+    // 依次执行步骤，返回第一个失败步骤的错误
+    // stop_on_failure 为 true 时遇到失败或中止即停止，否则执行全部步骤
+    async fn run_steps(
+        &self,
+        interpreter: &mut ScriptInterpreter,
+        steps: &[TksStep],
+        results: &mut Vec<StepResult>,
+        stop_on_failure: bool,
+    ) -> Option<String> {
+        let mut first_error = None;
+
+        for (index, step) in steps.iter().enumerate() {
+            // 检查是否需要停止
+            if stop_on_failure && self.should_stop {
+                return Some("执行被中止".to_string());
+            }
+
+            let step_result = interpreter.run_step(index, step).await;
+            // 标记了 允许失败 的步骤失败时继续执行，也不计入错误
+            let failed = !step_result.success && !step_result.allowed_failure;
+            if step_result.allowed_failure {
+                warn!("第{}行执行失败（允许失败）: {}", step.line_number, step_result.error.as_deref().unwrap_or_default());
+            }
+            if failed && first_error.is_none() {
+                first_error = Some(step_result.error.clone().unwrap_or_else(|| "步骤执行失败".to_string()));
+            }
+            results.push(step_result);
+
+            if failed && stop_on_failure {
+                break;
+            }
+
+            // 步骤间短暂延迟
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+
+        first_error
+    }
+
     // 停止执行
     pub fn stop(&mut self) {
         self.should_stop = true;
@@ -197,8 +280,10 @@ impl Runner {
         self.is_running
     }
     
-    // 保存执行结果
-    async fn save_result(&self, result: &ExecutionResult, script: &TksScript) -> Result<()> {
+    // 本次执行的结果目录 cases/<用例文件夹>/result/<执行名>/，返回 (目录, 执行名)
+    // 执行名优先使用脚本头部的用例ID和脚本名，缺省时使用文件夹名和文件名
+    // 数据驱动时包含数据行序号和名称（row 为 (序号, 名称)），避免同一秒内执行的多行写入同一目录
+    fn run_dir(&self, script: &TksScript, row: Option<(usize, &str)>) -> (PathBuf, String) {
         // 推断case文件夹名称
         let case_folder = if let Some(ref script_path) = script.file_path {
             // 从脚本路径推断case文件夹
@@ -210,26 +295,37 @@ impl Runner {
         } else {
             "temp"
         };
-        
-        // 创建result目录
-        let result_dir = self.project_path.join("cases").join(case_folder).join("result");
-        tokio::fs::create_dir_all(&result_dir).await
-            .map_err(|e| TkeError::IoError(e))?;
-        
-        // 生成结果文件名
+
         let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
-        let status = if result.success { "PASS" } else { "FAIL" };
-        let script_name = if let Some(ref script_path) = script.file_path {
+        let script_stem = if let Some(ref script_path) = script.file_path {
             script_path.file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("unknown_script")
         } else {
             "unknown_script"
         };
-        
-        let result_file_name = format!("{}_{}_{}_{}.json", 
-                                      case_folder, script_name, timestamp, status);
-        let result_path = result_dir.join(result_file_name);
+        let case_id = if script.case_id.is_empty() { case_folder } else { script.case_id.as_str() };
+        let script_name = if script.script_name.is_empty() { script_stem } else { script.script_name.as_str() };
+        let script_name = match row {
+            Some((index, key)) => format!("{}[{}-{}]", script_name, index + 1, key),
+            None => script_name.to_string(),
+        };
+
+        let run_name = format!("{}_{}_{}",
+                               Self::sanitize_file_name(case_id),
+                               Self::sanitize_file_name(&script_name),
+                               timestamp);
+        let run_dir = self.project_path.join("cases").join(case_folder).join("result").join(&run_name);
+        (run_dir, run_name)
+    }
+
+    // 保存执行结果到结果目录：<执行名>_<PASS|FAIL>.json
+    async fn save_result(&self, result: &ExecutionResult, run_dir: &PathBuf, run_name: &str) -> Result<()> {
+        tokio::fs::create_dir_all(run_dir).await
+            .map_err(|e| TkeError::IoError(e))?;
+
+        let status = if result.success { "PASS" } else { "FAIL" };
+        let result_path = run_dir.join(format!("{}_{}.json", run_name, status));
         
         // 保存结果
         let json = serde_json::to_string_pretty(result)
@@ -241,6 +337,17 @@ impl Runner {
         Ok(())
     }
     
+    // 将用例ID/脚本名转换为安全的文件名片段
+    fn sanitize_file_name(name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+                c if c.is_whitespace() => '_',
+                c => c,
+            })
+            .collect()
+    }
+    
     // 验证脚本
     pub fn validate_script(&self, script: &TksScript) -> Result<()> {
         if script.case_id.is_empty() {
@@ -254,6 +361,11 @@ impl Runner {
         Ok(())
     }
     
+    // 静态检查项目中的所有脚本及元素引用（不连接设备）
+    pub fn lint_project(&self) -> Result<LintReport> {
+        lint::lint_project(&self.project_path, &self.parser)
+    }
+
     // 运行项目中的所有脚本
     pub async fn run_project_scripts(&mut self) -> Result<Vec<ExecutionResult>> {
         let cases_dir = self.project_path.join("cases");
@@ -1,51 +1,111 @@
 // ScriptParser模块 - 负责解析.tks脚本文件
 
-use crate::{Result, TkeError, TksScript, TksStep, TksCommand, TksParam, Point};
+use super::diagnostic::{self, Diagnostic, ParamKind};
+use super::keywords::{KeywordTable, BlockKeyword, Section, Modifier};
+use super::shell::ShellAllowList;
+use crate::controller::{gesture, keycode};
+use crate::{Result, TkeError, TksScript, TksStep, TksCommand, TksParam, TksData, StepModifiers, AttributeCondition, Point, RelativeLocator, Anchor, Locale};
 use regex::Regex;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
 pub struct ScriptParser {
-    // 命令映射
-    command_map: HashMap<String, TksCommand>,
-    // 方向映射
-    direction_map: HashMap<String, String>,
+    // 关键字表（命令、方向、状态、块结构、头部字段）
+    keywords: KeywordTable,
+    // 项目路径（用于解析 调用 的子脚本路径）
+    project_path: Option<PathBuf>,
+    // 严格模式：存在错误级别的诊断时解析失败，而不是跳过无法识别的行
+    strict: bool,
+    // 项目的设备命令允许列表（用于检查 执行命令）
+    shell_allowlist: ShellAllowList,
 }
 
+// 头部中正在读取的多行字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum HeaderBlock {
+    None,
+    Details,    // 详情: 之后的 键: 值 和描述文本
+    Data,       // 数据: 之后的表格行
+}
+
+// XML元素支持的查找策略
+const XML_STRATEGIES: [&str; 5] = ["resourceId", "text", "className", "contentDesc", "xpath"];
+
 impl ScriptParser {
     pub fn new() -> Self {
-        let mut command_map = HashMap::new();
-        command_map.insert("启动".to_string(), TksCommand::Launch);
-        command_map.insert("关闭".to_string(), TksCommand::Close);
-        command_map.insert("点击".to_string(), TksCommand::Click);
-        command_map.insert("按压".to_string(), TksCommand::Press);
-        command_map.insert("滑动".to_string(), TksCommand::Swipe);
-        command_map.insert("定向滑动".to_string(), TksCommand::DirectionalSwipe);
-        command_map.insert("输入".to_string(), TksCommand::Input);
-        command_map.insert("清理".to_string(), TksCommand::Clear);
-        command_map.insert("隐藏键盘".to_string(), TksCommand::HideKeyboard);
-        command_map.insert("返回".to_string(), TksCommand::Back);
-        command_map.insert("等待".to_string(), TksCommand::Wait);
-        command_map.insert("断言".to_string(), TksCommand::Assert);
-        
-        let mut direction_map = HashMap::new();
-        direction_map.insert("上".to_string(), "up".to_string());
-        direction_map.insert("下".to_string(), "down".to_string());
-        direction_map.insert("左".to_string(), "left".to_string());
-        direction_map.insert("右".to_string(), "right".to_string());
-        
         Self {
-            command_map,
-            direction_map,
+            keywords: KeywordTable::builtin(),
+            project_path: None,
+            strict: false,
+            shell_allowlist: ShellAllowList::default(),
+        }
+    }
+
+    // 设置项目路径，并加载项目的关键字别名（keywords.json）和设备命令允许列表（shell_allowlist.json）
+    pub fn set_project_path(&mut self, project_path: PathBuf) {
+        let mut keywords = KeywordTable::builtin();
+        match keywords.load_project(&project_path) {
+            Ok(()) => self.keywords = keywords,
+            Err(e) => warn!("加载关键字别名失败，使用内置关键字: {}", e),
         }
+        match ShellAllowList::load_project(&project_path) {
+            Ok(allowlist) => self.shell_allowlist = allowlist,
+            Err(e) => warn!("加载设备命令允许列表失败: {}", e),
+        }
+        self.project_path = Some(project_path);
+    }
+
+    // 项目路径
+    pub(super) fn project_path(&self) -> Option<&PathBuf> {
+        self.project_path.as_ref()
+    }
+
+    // 关键字表
+    pub fn keywords(&self) -> &KeywordTable {
+        &self.keywords
+    }
+
+    // 获取所有命令名称，含英文关键字和别名（用于编辑器补全）
+    pub fn command_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.keywords.command_words().collect();
+        names.sort();
+        names
+    }
+
+    // 获取所有方向名称，含英文关键字和别名（用于编辑器补全）
+    pub fn direction_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.keywords.direction_words().collect();
+        names.sort();
+        names
+    }
+
+    // 根据名称查找命令
+    pub fn command(&self, name: &str) -> Option<&TksCommand> {
+        self.keywords.command(name)
+    }
+
+    // 设置严格模式
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
     }
     
     // 解析脚本文件
     pub fn parse_file(&self, script_path: &PathBuf) -> Result<TksScript> {
+        self.parse_file_with_stack(script_path, &mut Vec::new())
+    }
+
+    // 解析脚本文件，call_stack 为正在解析的调用链（用于检测循环调用）
+    fn parse_file_with_stack(&self, script_path: &PathBuf, call_stack: &mut Vec<PathBuf>) -> Result<TksScript> {
         let content = std::fs::read_to_string(script_path)
             .map_err(|e| TkeError::IoError(e))?;
-        
-        let mut script = self.parse(&content)?;
+
+        let canonical = script_path.canonicalize().unwrap_or_else(|_| script_path.clone());
+        call_stack.push(canonical);
+        let script = self.parse_content(&content, Some(script_path), call_stack);
+        call_stack.pop();
+
+        let mut script = script?;
         script.file_path = Some(script_path.clone());
         
         Ok(script)
@@ -53,18 +113,44 @@ impl ScriptParser {
     
     // 解析脚本内容
     pub fn parse(&self, content: &str) -> Result<TksScript> {
+        self.parse_content(content, None, &mut Vec::new())
+    }
+
+    // 解析脚本内容，script_path 为内容所在的文件（如果有）
+    fn parse_content(&self, content: &str, script_path: Option<&PathBuf>, call_stack: &mut Vec<PathBuf>) -> Result<TksScript> {
+        if self.strict {
+            let errors: Vec<String> = self.check_content(content, script_path, call_stack)
+                .into_iter()
+                .filter(|d| d.is_error())
+                .map(|d| format!("第{}行: {}", d.line, d.message))
+                .collect();
+            if !errors.is_empty() {
+                return Err(TkeError::ScriptParseError(errors.join("; ")));
+            }
+        }
+
         let lines: Vec<&str> = content.lines().collect();
 
         let mut script = TksScript {
             case_id: String::new(),
             script_name: String::new(),
             details: HashMap::new(),
+            tags: Vec::new(),
+            priority: None,
+            data: None,
+            setup: Vec::new(),
             steps: Vec::new(),
+            teardown: Vec::new(),
             file_path: None,
         };
 
-        // 找到 "步骤:" 标记
-        let mut in_steps = false;
+        // 当前所在的步骤段落（前置/步骤/后置），None 表示仍在脚本头部
+        let mut section: Option<Section> = None;
+        // 是否处于 "详情:" / "数据:" 块中
+        let mut header_block = HeaderBlock::None;
+
+        // 未闭合的块命令栈: (块命令步骤, 是否已进入否则分支)
+        let mut block_stack: Vec<(TksStep, bool)> = Vec::new();
 
         for (line_num, line) in lines.iter().enumerate() {
             let trimmed = line.trim();
@@ -74,115 +160,477 @@ impl ScriptParser {
                 continue;
             }
 
-            // 找到步骤部分
-            if trimmed == "步骤:" {
-                in_steps = true;
+            // 进入 前置/步骤/后置 段落（块命令不能跨段落）
+            if let Some(next) = self.keywords.step_section(trimmed) {
+                Self::check_blocks_closed(&block_stack)?;
+                section = Some(next);
                 continue;
             }
 
-            // 只解析步骤部分的内容
-            if in_steps {
-                if let Some(step) = self.parse_step(trimmed, line_num + 1) {
-                    script.steps.push(step);
+            // 步骤之前为脚本头部
+            let Some(current) = section else {
+                self.parse_header_line(trimmed, line_num + 1, &mut script, &mut header_block);
+                continue;
+            };
+            let steps = Self::section_steps(&mut script, current);
+
+            // 解析步骤部分的内容
+            match self.keywords.block(trimmed) {
+                Some(BlockKeyword::Else) => {
+                    match block_stack.last_mut() {
+                        Some((block, in_else)) if block.command == TksCommand::If && !*in_else => {
+                            *in_else = true;
+                        }
+                        _ => {
+                            return Err(TkeError::ScriptParseError(
+                                format!("第{}行: '否则' 没有对应的 '如果'", line_num + 1)
+                            ));
+                        }
+                    }
+                    continue;
+                }
+                Some(BlockKeyword::End) => {
+                    let (block, _) = block_stack.pop().ok_or_else(|| {
+                        TkeError::ScriptParseError(format!("第{}行: '结束' 没有对应的块命令", line_num + 1))
+                    })?;
+                    Self::push_step(&mut block_stack, steps, block);
+                    continue;
+                }
+                None => {}
+            }
+
+            if let Some(mut step) = self.parse_step(trimmed, line_num + 1) {
+                if let Some(message) = self.block_header_error(&step) {
+                    return Err(TkeError::ScriptParseError(
+                        format!("第{}行: {}", step.line_number, message)
+                    ));
+                }
+                if step.command == TksCommand::Call {
+                    self.load_callee(&mut step, script_path, call_stack)?;
+                }
+                if step.command.is_block() {
+                    block_stack.push((step, false));
+                } else {
+                    Self::push_step(&mut block_stack, steps, step);
                 }
             }
         }
 
+        Self::check_blocks_closed(&block_stack)?;
+
+        if let Some(data) = script.data.as_mut() {
+            self.load_data(data, script_path)?;
+        }
+
         Ok(script)
     }
+
+    // 解析 调用 的子脚本，将其步骤作为调用步骤的子步骤
+    fn load_callee(&self, step: &mut TksStep, script_path: Option<&PathBuf>, call_stack: &mut Vec<PathBuf>) -> Result<()> {
+        let target = match step.params.first() {
+            Some(TksParam::Text(path)) if !path.contains("${") => path.clone(),
+            _ => {
+                return Err(TkeError::ScriptParseError(
+                    format!("第{}行: '调用' 需要子脚本路径（不支持变量）", step.line_number)
+                ));
+            }
+        };
+
+        let callee_path = self.resolve_script_path(&target, script_path).ok_or_else(|| {
+            TkeError::ScriptParseError(format!("第{}行: 找不到子脚本 '{}'", step.line_number, target))
+        })?;
+
+        let canonical = callee_path.canonicalize().unwrap_or_else(|_| callee_path.clone());
+        if call_stack.contains(&canonical) {
+            let chain = call_stack.iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.file_name().and_then(|n| n.to_str()).unwrap_or("?").to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let file = script_path
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("脚本");
+            return Err(TkeError::ScriptParseError(
+                format!("{} 第{}行: 检测到循环调用: {}", file, step.line_number, chain)
+            ));
+        }
+
+        // 子脚本的前置、步骤和后置按顺序作为调用的子步骤
+        let callee = self.parse_file_with_stack(&callee_path, call_stack)?;
+        step.body = callee.setup.into_iter()
+            .chain(callee.steps)
+            .chain(callee.teardown)
+            .collect();
+        step.callee = Some(callee_path);
+
+        Ok(())
+    }
+
+    // 解析子脚本路径：绝对路径 > 相对项目目录 > 相对当前脚本所在目录
+    fn resolve_script_path(&self, target: &str, script_path: Option<&PathBuf>) -> Option<PathBuf> {
+        let target = Path::new(target);
+        if target.is_absolute() {
+            return target.exists().then(|| target.to_path_buf());
+        }
+
+        let candidates = [
+            self.project_path.as_ref().map(|p| p.join(target)),
+            script_path.and_then(|p| p.parent()).map(|dir| dir.join(target)),
+        ];
+
+        candidates.into_iter().flatten().find(|p| p.is_file())
+    }
+
+    // 校验循环块的次数参数（循环必须有明确的次数上限），返回错误信息
+    fn block_header_error(&self, step: &TksStep) -> Option<&'static str> {
+        // 次数可以是正整数，也可以是执行时才确定的变量
+        let has_count = |param: Option<&TksParam>| match param {
+            Some(TksParam::Number(n)) => *n > 0,
+            Some(TksParam::Text(t)) => t.contains("${"),
+            _ => false,
+        };
+
+        match step.command {
+            TksCommand::Repeat if !has_count(step.params.first()) => {
+                Some("'重复' 需要正整数次数，如 重复 3 次")
+            }
+            TksCommand::RepeatUntil if step.params.len() < 2 || !has_count(step.params.last()) => {
+                Some("'重复直到' 需要条件和最大次数，如 重复直到 [{元素}, 可见, 10]")
+            }
+            _ => None,
+        }
+    }
+
+    // 解析脚本头部的一行
+    // - 用例: / 脚本名: / 标签: / 优先级: 单行字段
+    // - 详情: 之后的 键: 值 行存入 details，其余文本行合并为 details["描述"]
+    // - 数据: 之后为数据文件路径，或以 | 分隔的表格行（第一行为列名）
+    pub(super) fn parse_header_line(&self, line: &str, line_number: usize, script: &mut TksScript, block: &mut HeaderBlock) {
+        if *block == HeaderBlock::Data && line.starts_with('|') {
+            if let Some(data) = script.data.as_mut() {
+                Self::push_table_row(data, line);
+            }
+            return;
+        }
+
+        let (key, value) = match line.split_once(':').or_else(|| line.split_once('：')) {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (line, None),
+        };
+
+        match (self.keywords.section(key), value) {
+            (Some(Section::CaseId), Some(value)) => {
+                script.case_id = value.to_string();
+                *block = HeaderBlock::None;
+            }
+            (Some(Section::ScriptName), Some(value)) => {
+                script.script_name = value.to_string();
+                *block = HeaderBlock::None;
+            }
+            (Some(Section::Tags), Some(value)) => {
+                script.tags = value
+                    .split(|c: char| c == ',' || c == '，' || c.is_whitespace())
+                    .map(|t| t.trim())
+                    .filter(|t| !t.is_empty())
+                    .map(|t| t.to_string())
+                    .collect();
+                *block = HeaderBlock::None;
+            }
+            (Some(Section::Priority), Some(value)) => {
+                script.priority = Some(value.to_string()).filter(|p| !p.is_empty());
+                *block = HeaderBlock::None;
+            }
+            (Some(Section::Details), Some(value)) => {
+                *block = HeaderBlock::Details;
+                if !value.is_empty() {
+                    Self::append_description(script, value);
+                }
+            }
+            (Some(Section::Data), Some(value)) => {
+                *block = HeaderBlock::Data;
+                script.data = Some(TksData {
+                    source: Some(value.to_string()).filter(|v| !v.is_empty()),
+                    line_number,
+                    ..Default::default()
+                });
+            }
+            (_, Some(value)) if *block == HeaderBlock::Details && !key.is_empty() => {
+                script.details.insert(key.to_string(), value.to_string());
+            }
+            _ if *block == HeaderBlock::Details => Self::append_description(script, line),
+            _ => *block = HeaderBlock::None,
+        }
+    }
+
+    // 添加数据表格的一行（第一行为列名，跳过 |---|---| 分隔行）
+    fn push_table_row(data: &mut TksData, line: &str) {
+        let cells = Self::table_cells(line);
+        if cells.iter().all(|c| !c.is_empty() && c.chars().all(|ch| ch == '-' || ch == ':')) {
+            return;
+        }
+
+        if data.columns.is_empty() {
+            data.columns = cells;
+        } else {
+            data.rows.push(cells);
+        }
+    }
+
+    // 拆分表格行 | 列1 | 列2 | 的单元格
+    pub(super) fn table_cells(line: &str) -> Vec<String> {
+        let inner = line.trim().strip_prefix('|').unwrap_or(line);
+        let inner = inner.strip_suffix('|').unwrap_or(inner);
+        inner.split('|').map(|cell| cell.trim().to_string()).collect()
+    }
+
+    // 追加详情中的描述文本
+    fn append_description(script: &mut TksScript, text: &str) {
+        script.details.entry("描述".to_string())
+            .and_modify(|desc| {
+                desc.push('\n');
+                desc.push_str(text);
+            })
+            .or_insert_with(|| text.to_string());
+    }
+
+    // 段落对应的步骤列表
+    fn section_steps(script: &mut TksScript, section: Section) -> &mut Vec<TksStep> {
+        match section {
+            Section::Setup => &mut script.setup,
+            Section::Teardown => &mut script.teardown,
+            _ => &mut script.steps,
+        }
+    }
+
+    // 检查块命令是否都已闭合
+    fn check_blocks_closed(block_stack: &[(TksStep, bool)]) -> Result<()> {
+        match block_stack.last() {
+            Some((block, _)) => Err(TkeError::ScriptParseError(
+                format!("第{}行: '{}' 缺少对应的 '结束'", block.line_number, block.raw)
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // 将步骤添加到当前所在的块中（不在块中则添加到脚本顶层）
+    fn push_step(block_stack: &mut [(TksStep, bool)], steps: &mut Vec<TksStep>, step: TksStep) {
+        match block_stack.last_mut() {
+            Some((block, true)) => block.else_body.push(step),
+            Some((block, false)) => block.body.push(step),
+            None => steps.push(step),
+        }
+    }
     
     // 解析单个步骤
-    fn parse_step(&self, line: &str, line_number: usize) -> Option<TksStep> {
+    pub(super) fn parse_step(&self, line: &str, line_number: usize) -> Option<TksStep> {
         // 匹配命令格式
         // 格式1: 命令 [参数1, 参数2]
         // 格式2: 命令 参数1 参数2
         // 格式3: 命令
-        
-        let bracket_re = Regex::new(r"^(\S+)\s*\[(.*)\]$").ok()?;
-        let simple_re = Regex::new(r"^(\S+)(?:\s+(.*))?$").ok()?;
-        
-        let (command_str, params_str) = if let Some(caps) = bracket_re.captures(line) {
-            // 方括号格式
-            let cmd = caps.get(1)?.as_str();
-            let params = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-            (cmd, params)
-        } else if let Some(caps) = simple_re.captures(line) {
-            // 简单格式
-            let cmd = caps.get(1)?.as_str();
-            let params = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-            (cmd, params)
-        } else {
-            return None;
-        };
+        // 以上格式均可追加期望值: 命令 [参数] == 期望文本
+        // 行尾可追加修饰符: 命令 [参数] 重试=3 超时=10s 允许失败
+
+        let (line_body, modifier_words) = self.split_modifiers(line);
+        let (line_body, expected) = self.split_expectation(line_body);
+
+        let (command_str, _, params_str, _) = Self::split_command(line_body)?;
         
         // 查找命令类型
-        let command = self.command_map.get(command_str)?;
+        let command = self.keywords.command(command_str)?;
         
         // 解析参数
-        let params = self.parse_parameters(params_str);
+        let params = self.parse_parameters(command, params_str);
         
         Some(TksStep {
             command: command.clone(),
             params,
+            expected,
+            body: Vec::new(),
+            else_body: Vec::new(),
+            callee: None,
+            modifiers: self.parse_modifiers(&modifier_words).0,
             raw: line.to_string(),
             line_number,
         })
     }
-    
-    // 解析参数
-    fn parse_parameters(&self, params_str: &str) -> Vec<TksParam> {
-        if params_str.is_empty() {
-            return Vec::new();
+
+    // 拆分行尾的步骤修饰符，返回 (步骤文本, 修饰符列表)
+    pub(super) fn split_modifiers<'a>(&self, line: &'a str) -> (&'a str, Vec<&'a str>) {
+        let mut body = line.trim_end();
+        let mut words = Vec::new();
+
+        while let Some((rest, word)) = body.rsplit_once(char::is_whitespace) {
+            if self.modifier_of(word).is_none() {
+                break;
+            }
+            words.insert(0, word);
+            body = rest.trim_end();
         }
-        
-        let mut params = Vec::new();
-        let mut current = String::new();
+
+        (body, words)
+    }
+
+    // 修饰符对应的关键字（重试=3 -> 重试，允许失败 -> 允许失败）
+    fn modifier_of(&self, word: &str) -> Option<Modifier> {
+        match word.split_once('=') {
+            Some((key, _)) => self.keywords.modifier(key).filter(|m| m.takes_value()),
+            None => self.keywords.modifier(word).filter(|m| !m.takes_value()),
+        }
+    }
+
+    // 解析修饰符，返回 (修饰符, 无效的修饰符及错误信息)
+    pub(super) fn parse_modifiers<'a>(&self, words: &[&'a str]) -> (StepModifiers, Vec<(&'a str, String)>) {
+        let mut modifiers = StepModifiers::default();
+        let mut errors = Vec::new();
+
+        for word in words {
+            let value = word.split_once('=').map(|(_, value)| value.trim()).unwrap_or_default();
+            match self.modifier_of(word) {
+                Some(Modifier::Retry) => match value.parse::<u32>() {
+                    Ok(times) => modifiers.retry = times,
+                    Err(_) => errors.push((*word, format!("重试次数应为非负整数，如 重试=3，实际为 '{}'", value))),
+                },
+                Some(Modifier::Timeout) => match Self::parse_timeout(value) {
+                    Some(ms) => modifiers.timeout_ms = Some(ms),
+                    None => errors.push((*word, format!("超时应为正的时长，如 超时=10s 或 超时=500ms，实际为 '{}'", value))),
+                },
+                Some(Modifier::AllowFailure) => modifiers.allow_failure = true,
+                None => {}
+            }
+        }
+
+        (modifiers, errors)
+    }
+
+    // 解析超时时长（10s / 500ms / 纯数字为秒数），返回毫秒数
+    fn parse_timeout(value: &str) -> Option<u32> {
+        let ms = if let Some(ms) = value.strip_suffix("ms") {
+            ms.parse::<u32>().ok()
+        } else {
+            value.strip_suffix('s').unwrap_or(value)
+                .parse::<u32>().ok()
+                .and_then(|seconds| seconds.checked_mul(1000))
+        };
+        ms.filter(|ms| *ms > 0)
+    }
+
+    // 拆分命令名和参数，返回 (命令, 参数在行内的起始位置, 参数文本, 是否为方括号格式)
+    pub(super) fn split_command(line: &str) -> Option<(&str, usize, &str, bool)> {
+        let bracket_re = Regex::new(r"^(\S+)\s*\[(.*)\]$").ok()?;
+        let simple_re = Regex::new(r"^(\S+)(?:\s+(.*))?$").ok()?;
+
+        if let Some(caps) = bracket_re.captures(line) {
+            // 方括号格式
+            let params = caps.get(2)?;
+            Some((caps.get(1)?.as_str(), params.start(), params.as_str(), true))
+        } else if let Some(caps) = simple_re.captures(line) {
+            // 简单格式
+            let (start, params) = caps.get(2)
+                .map(|m| (m.start(), m.as_str()))
+                .unwrap_or((line.len(), ""));
+            Some((caps.get(1)?.as_str(), start, params, false))
+        } else {
+            None
+        }
+    }
+
+    // 拆分行尾的期望值 "== 期望文本"（忽略括号和引号内的 ==）
+    pub(super) fn split_expectation<'a>(&self, line: &'a str) -> (&'a str, Option<String>) {
+        let mut depth = 0i32;
         let mut in_quotes = false;
         let mut quote_char = ' ';
-        let mut bracket_depth = 0;
-        
-        for ch in params_str.chars() {
+        let mut prev: Option<(usize, char)> = None;
+
+        for (pos, ch) in line.char_indices() {
             match ch {
                 '"' | '\'' if !in_quotes => {
                     in_quotes = true;
                     quote_char = ch;
-                    current.push(ch);
-                }
-                c if c == quote_char && in_quotes => {
-                    in_quotes = false;
-                    current.push(ch);
                 }
-                '{' if !in_quotes => {
-                    bracket_depth += 1;
-                    current.push(ch);
+                c if c == quote_char && in_quotes => in_quotes = false,
+                '[' | '{' if !in_quotes => depth += 1,
+                ']' | '}' if !in_quotes => depth -= 1,
+                '=' if !in_quotes && depth == 0 => {
+                    if let Some((prev_pos, '=')) = prev {
+                        let body = line[..prev_pos].trim_end();
+                        let value = line[pos + 1..].trim();
+                        let value = if value.len() >= 2 &&
+                            ((value.starts_with('"') && value.ends_with('"')) ||
+                             (value.starts_with('\'') && value.ends_with('\''))) {
+                            &value[1..value.len() - 1]
+                        } else {
+                            value
+                        };
+                        return (body, Some(value.to_string()));
+                    }
                 }
-                '}' if !in_quotes => {
-                    bracket_depth -= 1;
-                    current.push(ch);
+                _ => {}
+            }
+            prev = Some((pos, ch));
+        }
+
+        (line, None)
+    }
+    
+    // 解析参数，按命令签名确定每个位置的参数类型
+    fn parse_parameters(&self, command: &TksCommand, params_str: &str) -> Vec<TksParam> {
+        Self::split_parameters(params_str)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, param))| self.parse_parameter(param, Self::param_kind(command, index)))
+            .collect()
+    }
+
+    // 命令第 index 个参数的类型（超出签名的参数为 None）
+    pub(super) fn param_kind(command: &TksCommand, index: usize) -> Option<ParamKind> {
+        diagnostic::signature(command).params.get(index).map(|(_, kind)| *kind)
+    }
+
+    // 按逗号拆分参数（忽略引号和大括号内的逗号），返回 (参数起始位置, 参数文本)
+    pub(super) fn split_parameters(params_str: &str) -> Vec<(usize, &str)> {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        let mut in_quotes = false;
+        let mut quote_char = ' ';
+        let mut bracket_depth = 0;
+
+        for (pos, ch) in params_str.char_indices() {
+            match ch {
+                '"' | '\'' if !in_quotes => {
+                    in_quotes = true;
+                    quote_char = ch;
                 }
+                c if c == quote_char && in_quotes => in_quotes = false,
+                '{' if !in_quotes => bracket_depth += 1,
+                '}' if !in_quotes => bracket_depth -= 1,
                 ',' if !in_quotes && bracket_depth == 0 => {
                     // 参数分隔符
-                    if !current.trim().is_empty() {
-                        params.push(self.parse_parameter(current.trim()));
-                    }
-                    current.clear();
-                }
-                _ => {
-                    current.push(ch);
+                    Self::push_parameter(&mut parts, params_str, start, pos);
+                    start = pos + 1;
                 }
+                _ => {}
             }
         }
-        
+
         // 处理最后一个参数
-        if !current.trim().is_empty() {
-            params.push(self.parse_parameter(current.trim()));
+        Self::push_parameter(&mut parts, params_str, start, params_str.len());
+
+        parts
+    }
+
+    // 添加去除首尾空白后的非空参数
+    fn push_parameter<'a>(parts: &mut Vec<(usize, &'a str)>, params_str: &'a str, start: usize, end: usize) {
+        let raw = &params_str[start..end];
+        let trimmed = raw.trim();
+        if !trimmed.is_empty() {
+            parts.push((start + raw.len() - raw.trim_start().len(), trimmed));
         }
-        
-        params
     }
     
     // 解析单个参数
-    fn parse_parameter(&self, param: &str) -> TksParam {
+    // kind 为该位置的参数类型：方向、状态和属性条件关键字只在对应位置解析，其他位置（输入文本、变量值等）保持为文本
+    pub(super) fn parse_parameter(&self, param: &str, kind: Option<ParamKind>) -> TksParam {
         let param = param.trim();
         
         // 移除引号
@@ -196,6 +644,25 @@ impl ScriptParser {
             return TksParam::Number(num);
         }
         
+        // 解析次数（如 3次 / 3 times）
+        if let Some(count) = self.keywords.strip_count_suffix(param) {
+            let count = count.trim();
+            if let Ok(num) = count.parse::<i32>() {
+                return TksParam::Number(num);
+            }
+            // 变量次数（如 ${n} 次）去掉后缀，执行时插值为数字
+            if count.contains("${") {
+                return TksParam::Text(count.to_string());
+            }
+        }
+
+        // 解析时间（如 500ms）
+        if let Some(ms) = param.strip_suffix("ms") {
+            if let Ok(ms) = ms.parse::<u32>() {
+                return TksParam::Duration(ms);
+            }
+        }
+
         // 解析时间（如 10s）
         if param.ends_with('s') {
             if let Ok(seconds) = param[..param.len()-1].parse::<u32>() {
@@ -213,8 +680,8 @@ impl ScriptParser {
         
         // 解析坐标 {x,y} 或 XML元素引用 {元素名} 或 {元素名}&策略
         if param.starts_with('{') && !param.contains('@') {
-            // 找到右大括号的位置
-            if let Some(close_brace_pos) = param.find('}') {
+            // 找到与开头匹配的右大括号位置（名称中可能包含 ${变量}）
+            if let Some(close_brace_pos) = Self::find_closing_brace(param) {
                 let inner = &param[1..close_brace_pos];
                 let after_brace = &param[close_brace_pos+1..];
 
@@ -229,33 +696,38 @@ impl ScriptParser {
                     }
                 }
 
-                // 否则为XML元素引用，解析可选的策略
+                // 否则为XML元素引用，解析可选的策略和相对定位
                 // 格式1: {元素名} - 全精确匹配
                 // 格式2: {元素名}&resourceId - 使用 resourceId 策略
                 // 格式3: {元素名}&text - 使用 text 策略
                 // 格式4: {元素名}&className - 使用 className 策略
                 // 格式5: {元素名}&contentDesc - 使用 contentDesc 策略
                 // 格式6: {元素名}&xpath - 使用 xpath 策略
+                // 格式7: {元素名}[&策略] 关系 {锚点} / "锚点文本" - 相对定位
+                let (strategy_part, relation_part) = match after_brace.strip_prefix('&') {
+                    Some(rest) => rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest, "")),
+                    None => ("", after_brace),
+                };
+                let relative = Self::parse_relative(relation_part);
                 let (name, strategy) = if after_brace.starts_with('&') {
                     let element_name = inner.trim().to_string();
-                    let strategy_name = after_brace[1..].trim().to_string();
+                    let strategy_name = strategy_part.trim().to_string();
 
                     // 验证策略名称
-                    match strategy_name.as_str() {
-                        "resourceId" | "text" | "className" | "contentDesc" | "xpath" => {
-                            (element_name, Some(strategy_name))
-                        }
-                        _ => {
-                            // 无效的策略名称，忽略策略
-                            (inner.to_string(), None)
-                        }
+                    if XML_STRATEGIES.contains(&strategy_name.as_str()) {
+                        (element_name, Some(strategy_name))
+                    } else {
+                        // 无效的策略名称，忽略策略
+                        (inner.to_string(), None)
                     }
+                } else if relative.is_some() {
+                    (inner.trim().to_string(), None)
                 } else {
                     // 没有指定策略
                     (inner.to_string(), None)
                 };
 
-                return TksParam::XmlElement { name, strategy };
+                return TksParam::XmlElement { name, strategy, relative };
             }
         }
         
@@ -265,22 +737,741 @@ impl ScriptParser {
             return TksParam::ImageElement(image_name);
         }
         
-        // 解析方向
-        if let Some(direction) = self.direction_map.get(param) {
-            return TksParam::Direction(direction.clone());
+        // 解析元素属性条件（已勾选 / 文本包含=登录），只用于断言和条件块的条件
+        if kind == Some(ParamKind::State) {
+            if let Some(condition) = self.keywords.attribute(param) {
+                return TksParam::Attribute(condition);
+            }
+        }
+
+        // 解析方向，只用于方向参数（up/down 等英文词在其他位置可以直接作为文本）
+        if kind == Some(ParamKind::Direction) {
+            if let Some(direction) = self.keywords.direction(param) {
+                return TksParam::Direction(direction.to_string());
+            }
         }
         
-        // 解析布尔值
-        match param {
-            "存在" | "true" => return TksParam::Boolean(true),
-            "不存在" | "false" => return TksParam::Boolean(false),
-            _ => {}
+        // 解析元素状态（存在/不存在/可见/不可见）和布尔值，只用于条件和等待的状态
+        if matches!(kind, Some(ParamKind::State | ParamKind::WaitOption)) {
+            match (self.keywords.state(param), param) {
+                (Some((true, visible)), _) => return TksParam::Visibility(visible),
+                (Some((false, exists)), _) => return TksParam::Boolean(exists),
+                (None, "true") => return TksParam::Boolean(true),
+                (None, "false") => return TksParam::Boolean(false),
+                _ => {}
+            }
         }
         
         // 默认返回文本
         TksParam::Text(param.to_string())
     }
     
+    // 解析元素之后的相对定位: 关系 {锚点} 或 关系 "锚点文本"，格式不正确时返回 None
+    fn parse_relative(text: &str) -> Option<RelativeLocator> {
+        let (keyword, anchor) = text.trim().split_once(char::is_whitespace)?;
+        let relation = KeywordTable::relation(keyword)?;
+        let anchor = anchor.trim();
+
+        let anchor = if anchor.starts_with('{') && Self::find_closing_brace(anchor) == Some(anchor.len() - 1) {
+            Anchor::Element(anchor[1..anchor.len() - 1].trim().to_string())
+        } else if anchor.len() >= 2 && (
+            (anchor.starts_with('"') && anchor.ends_with('"')) ||
+            (anchor.starts_with('\'') && anchor.ends_with('\''))) {
+            Anchor::Text(anchor[1..anchor.len() - 1].to_string())
+        } else {
+            return None;
+        };
+
+        Some(RelativeLocator { relation, anchor })
+    }
+
+    // 将带相对定位的元素参数拆分为 (目标部分, 锚点部分)，如 "{开关}&text 右侧 {标签}" -> ("{开关}&text", "{标签}")
+    pub(super) fn split_relative(raw: &str) -> (&str, &str) {
+        let Some(close) = Self::find_closing_brace(raw) else {
+            return (raw, "");
+        };
+        let after = &raw[close + 1..];
+        let target_end = match after.strip_prefix('&') {
+            Some(rest) => {
+                let rest = rest.trim_start();
+                let strategy_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                raw.len() - rest.len() + strategy_len
+            }
+            None => close + 1,
+        };
+
+        let relation = raw[target_end..].trim_start();
+        let anchor = relation.split_once(char::is_whitespace).map_or("", |(_, anchor)| anchor.trim());
+        (&raw[..target_end], anchor)
+    }
+
+    // 查找与第一个左大括号匹配的右大括号位置
+    fn find_closing_brace(param: &str) -> Option<usize> {
+        let mut depth = 0;
+        for (pos, ch) in param.char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // 检查脚本文件，返回全部诊断信息
+    pub fn check_file(&self, script_path: &PathBuf) -> Result<Vec<Diagnostic>> {
+        let content = std::fs::read_to_string(script_path)
+            .map_err(TkeError::IoError)?;
+
+        Ok(self.check_source(&content, script_path))
+    }
+
+    // 检查脚本内容，返回全部诊断信息
+    pub fn check(&self, content: &str) -> Vec<Diagnostic> {
+        self.check_content(content, None, &mut Vec::new())
+    }
+
+    // 检查尚未保存的脚本内容（如编辑器中的文档），子脚本相对 script_path 解析
+    pub fn check_source(&self, content: &str, script_path: &PathBuf) -> Vec<Diagnostic> {
+        let canonical = script_path.canonicalize().unwrap_or_else(|_| script_path.clone());
+        self.check_content(content, Some(script_path), &mut vec![canonical])
+    }
+
+    // 逐行检查脚本，遇到问题不中断，收集所有诊断
+    fn check_content(&self, content: &str, script_path: Option<&PathBuf>, call_stack: &mut Vec<PathBuf>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut header = TksScript {
+            case_id: String::new(),
+            script_name: String::new(),
+            details: HashMap::new(),
+            tags: Vec::new(),
+            priority: None,
+            data: None,
+            setup: Vec::new(),
+            steps: Vec::new(),
+            teardown: Vec::new(),
+            file_path: None,
+        };
+        let mut section: Option<Section> = None;
+        let mut header_block = HeaderBlock::None;
+        let mut step_count = 0;
+
+        // 未闭合的块命令栈: (块命令, 行号, 命令名列范围, 是否已进入否则分支)
+        let mut block_stack: Vec<(TksCommand, usize, (usize, usize), bool)> = Vec::new();
+
+        for (line_num, line) in content.lines().enumerate() {
+            let line_number = line_num + 1;
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            // 块命令不能跨 前置/步骤/后置 段落
+            if let Some(next) = self.keywords.step_section(trimmed) {
+                Self::report_unclosed(&mut block_stack, &mut diagnostics);
+                section = Some(next);
+                continue;
+            }
+
+            let Some(current) = section else {
+                self.parse_header_line(trimmed, line_number, &mut header, &mut header_block);
+                continue;
+            };
+
+            let (start, end) = Self::columns(line, trimmed);
+            match self.keywords.block(trimmed) {
+                Some(BlockKeyword::Else) => {
+                    match block_stack.last_mut() {
+                        Some((TksCommand::If, _, _, in_else)) if !*in_else => *in_else = true,
+                        _ => diagnostics.push(Diagnostic::error(line_number, start, end, "'否则' 没有对应的 '如果'")),
+                    }
+                    continue;
+                }
+                Some(BlockKeyword::End) => {
+                    if block_stack.pop().is_none() {
+                        diagnostics.push(Diagnostic::error(line_number, start, end, "'结束' 没有对应的块命令"));
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            if let Some(step) = self.check_step(line, line_number, script_path, call_stack, &mut diagnostics) {
+                if current == Section::Steps {
+                    step_count += 1;
+                }
+                if step.command.is_block() {
+                    let offset = line.len() - line.trim_start().len();
+                    let command_len = trimmed.split(|c: char| c.is_whitespace() || c == '[').next().unwrap_or("").len();
+                    let span = Self::columns(line, &line[offset..offset + command_len]);
+                    block_stack.push((step.command, line_number, span, false));
+                }
+            }
+        }
+
+        Self::report_unclosed(&mut block_stack, &mut diagnostics);
+
+        // 数据文件能否加载、每行列数是否与表头一致
+        if let Some(data) = header.data.as_mut() {
+            if let Err(e) = self.load_data(data, script_path) {
+                let prefix = format!("第{}行: ", data.line_number);
+                let message = match e {
+                    TkeError::ScriptParseError(message) => message.strip_prefix(&prefix).unwrap_or(&message).to_string(),
+                    e => e.to_string(),
+                };
+                let line = content.lines().nth(data.line_number - 1).unwrap_or_default();
+                let (start, end) = Self::columns(line, line.trim());
+                diagnostics.push(Diagnostic::error(data.line_number, start, end, message));
+            }
+        }
+
+        if header.case_id.is_empty() {
+            diagnostics.push(Diagnostic::warning(1, 0, 0, "脚本缺少用例ID（用例: ...）"));
+        }
+        if step_count == 0 {
+            diagnostics.push(Diagnostic::warning(1, 0, 0, "脚本没有定义任何步骤"));
+        }
+
+        diagnostics.sort_by_key(|d| (d.line, d.start));
+        diagnostics
+    }
+
+    // 报告未闭合的块命令并清空块命令栈
+    fn report_unclosed(block_stack: &mut Vec<(TksCommand, usize, (usize, usize), bool)>, diagnostics: &mut Vec<Diagnostic>) {
+        for (command, line_number, (start, end), _) in block_stack.drain(..) {
+            diagnostics.push(Diagnostic::error(
+                line_number, start, end,
+                format!("'{}' 缺少对应的 '结束'", command.to_str()),
+            ));
+        }
+    }
+
+    // 检查单个步骤行，命令可识别时返回解析出的步骤
+    fn check_step(
+        &self,
+        line: &str,
+        line_number: usize,
+        script_path: Option<&PathBuf>,
+        call_stack: &mut Vec<PathBuf>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<TksStep> {
+        let text = line.trim();
+        let offset = line.len() - line.trim_start().len();
+        let (step_text, modifier_words) = self.split_modifiers(text);
+        let (body, expected) = self.split_expectation(step_text);
+        let (command_str, params_start, params_str, bracketed) = Self::split_command(body)?;
+
+        // 行内子串的字符列范围
+        let span = |part: &str| Self::columns(line, part);
+        let error = |part: &str, message: String| {
+            let (start, end) = span(part);
+            Diagnostic::error(line_number, start, end, message)
+        };
+
+        let command_span = &line[offset..offset + command_str.len()];
+        let Some(command) = self.keywords.command(command_str) else {
+            let mut d = error(command_span, format!("未知命令 '{}'", command_str));
+            if let Some(candidate) = diagnostic::closest_match(command_str, self.keywords.command_words()) {
+                d.message.push_str(&format!("，是否为 '{}'?", candidate));
+                d = d.with_suggestion(candidate);
+            }
+            diagnostics.push(d);
+            return None;
+        };
+
+        if !bracketed && params_str.trim_start().starts_with('[') {
+            diagnostics.push(error(params_str, "方括号不匹配，或 ']' 之后有多余内容".to_string()));
+            return None;
+        }
+
+        // 检查参数的括号和查找策略
+        let raw_params = Self::split_parameters(params_str);
+        let mut malformed = false;
+        for (_, raw) in &raw_params {
+            if let Some(d) = Self::check_parameter_syntax(raw, &error) {
+                diagnostics.push(d);
+                malformed = true;
+            }
+        }
+        if malformed {
+            return None;
+        }
+
+        let step = self.parse_step(text, line_number)?;
+        let param_spans: Vec<&str> = raw_params.iter()
+            .map(|(start, raw)| {
+                let start = offset + params_start + start;
+                &line[start..start + raw.len()]
+            })
+            .collect();
+
+        match step.command {
+            TksCommand::If => {
+                self.check_condition(&step.params, &param_spans, expected.is_some(), command_span, &error, diagnostics);
+            }
+            TksCommand::Repeat | TksCommand::RepeatUntil => {
+                if let Some(message) = self.block_header_error(&step) {
+                    diagnostics.push(error(command_span, message.to_string()));
+                } else if step.command == TksCommand::RepeatUntil {
+                    let count = step.params.len() - 1;
+                    self.check_condition(&step.params[..count], &param_spans[..count], expected.is_some(), command_span, &error, diagnostics);
+                }
+            }
+            TksCommand::Wait => {
+                Self::check_wait(&step.params, &param_spans, &error, diagnostics);
+            }
+            _ => {
+                Self::check_signature(command, &step.params, &param_spans, command_span, &error, diagnostics);
+            }
+        }
+
+        if matches!(step.command, TksCommand::Assert | TksCommand::If | TksCommand::RepeatUntil) {
+            Self::check_attribute_condition(&step.params, &param_spans, &error, diagnostics);
+        }
+
+        if step.command == TksCommand::Key {
+            Self::check_key(&step.params, &param_spans, &error, diagnostics);
+        }
+
+        // 多指滑动的手指数
+        if let (TksCommand::MultiSwipe, Some(TksParam::Number(fingers))) = (&step.command, step.params.get(3)) {
+            if !(2..=gesture::MAX_FINGERS as i32).contains(fingers) {
+                diagnostics.push(error(param_spans[3], format!("手指数必须在 2 到 {} 之间", gesture::MAX_FINGERS)));
+            }
+        }
+
+        // 安装选项只支持 -r（覆盖安装）和 -g（授予所有运行时权限）
+        if let (TksCommand::Install, Some(TksParam::Text(options))) = (&step.command, step.params.get(1)) {
+            if let Some(option) = options.split_whitespace().find(|o| !matches!(*o, "-r" | "-g")) {
+                diagnostics.push(error(param_spans[1], format!("未知的安装选项 '{}'，只支持 -r 和 -g", option)));
+            }
+        }
+
+        if step.command == TksCommand::Shell {
+            self.check_shell(&step.params, &param_spans, &error, diagnostics);
+        }
+
+        // 读取坐标区域需要宽度和高度
+        if step.command == TksCommand::Read
+            && matches!(step.params.first(), Some(TksParam::Coordinate(_)))
+            && step.params.len() < 3 {
+            diagnostics.push(error(command_span, "读取坐标区域需要宽度和高度，如 读取 [{500,300}, 200, 80]".to_string()));
+        }
+
+        // 只有 读取 和条件块支持期望值
+        if expected.is_some() && !matches!(step.command, TksCommand::Read | TksCommand::If | TksCommand::RepeatUntil) {
+            let (start, end) = span(&line[offset + body.len()..offset + step_text.len()]);
+            diagnostics.push(Diagnostic::warning(
+                line_number, start, end,
+                format!("'{}' 不支持期望值，'==' 之后的内容将被忽略", command_str),
+            ));
+        }
+
+        if step.command == TksCommand::Call {
+            self.check_callee(&step, script_path, call_stack, &param_spans, &error, diagnostics);
+        }
+
+        for (word, message) in self.parse_modifiers(&modifier_words).1 {
+            diagnostics.push(error(word, message));
+        }
+
+        Some(step)
+    }
+
+    // 检查参数中的大括号和 &策略 后缀
+    fn check_parameter_syntax(raw: &str, error: &impl Fn(&str, String) -> Diagnostic) -> Option<Diagnostic> {
+        let brace_start = if raw.starts_with("@{") {
+            1
+        } else if raw.starts_with('{') {
+            0
+        } else {
+            return None;
+        };
+
+        let Some(close) = Self::find_closing_brace(&raw[brace_start..]).map(|p| p + brace_start) else {
+            return Some(error(raw, "缺少右大括号 '}'".to_string()));
+        };
+
+        let after = &raw[close + 1..];
+        if after.is_empty() {
+            return None;
+        }
+
+        match after.strip_prefix('&') {
+            Some(rest) if brace_start == 0 => {
+                let (strategy, relation) = rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                if !XML_STRATEGIES.contains(&strategy) {
+                    let mut d = error(after, format!(
+                        "无效的查找策略 '{}'，可选: {}", strategy, XML_STRATEGIES.join(", ")
+                    ));
+                    if let Some(candidate) = diagnostic::closest_match(strategy, XML_STRATEGIES) {
+                        d = d.with_suggestion(format!("&{}", candidate));
+                    }
+                    return Some(d);
+                }
+                Self::check_relative(relation, error)
+            }
+            None if brace_start == 0 && after.starts_with(char::is_whitespace) => Self::check_relative(after, error),
+            _ => Some(error(after, "'}' 之后有多余内容".to_string())),
+        }
+    }
+
+    // 检查元素之后的相对定位: 关系 {锚点} 或 关系 "锚点文本"
+    fn check_relative(text: &str, error: &impl Fn(&str, String) -> Diagnostic) -> Option<Diagnostic> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        let (keyword, anchor) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        if KeywordTable::relation(keyword).is_none() {
+            let candidates: Vec<&str> = [Locale::Zh, Locale::En].into_iter().flat_map(KeywordTable::relation_keywords).collect();
+            let mut d = error(keyword, format!("无效的相对定位关系 '{}'，可选: {}", keyword, candidates.join(", ")));
+            if let Some(candidate) = diagnostic::closest_match(keyword, candidates) {
+                d = d.with_suggestion(candidate.to_string());
+            }
+            return Some(d);
+        }
+
+        let anchor = anchor.trim();
+        if anchor.is_empty() {
+            return Some(error(keyword, format!("'{}' 之后缺少锚点，如 {{开关}} 右侧 {{Wi-Fi标签}}", keyword)));
+        }
+        if Self::parse_relative(text).is_none() {
+            return Some(error(anchor, "锚点应为 {元素名} 或带引号的文本".to_string()));
+        }
+
+        None
+    }
+
+    // 按命令签名检查参数个数和类型
+    fn check_signature(
+        command: &TksCommand,
+        params: &[TksParam],
+        spans: &[&str],
+        command_span: &str,
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let signature = diagnostic::signature(command);
+
+        if params.len() < signature.required {
+            let names: Vec<&str> = signature.params[..signature.required].iter().map(|(name, _)| *name).collect();
+            diagnostics.push(error(command_span, format!(
+                "'{}' 需要 {} 个参数 ({})，实际为 {} 个",
+                command.to_str(), signature.required, names.join(", "), params.len()
+            )));
+        }
+
+        for (index, (param, span)) in params.iter().zip(spans).enumerate() {
+            match signature.params.get(index) {
+                Some((name, kind)) if !kind.accepts(param) => {
+                    let mut d = error(span, format!("参数{} ({}) 应为{}", index + 1, name, kind.describe()));
+                    // 纯数字会被解析为数字，文本参数需要加引号
+                    if *kind == diagnostic::ParamKind::Text && !span.starts_with('{') && !span.starts_with('@') {
+                        d = d.with_suggestion(format!("\"{}\"", span));
+                    }
+                    diagnostics.push(d);
+                }
+                Some(_) => {}
+                None if !signature.variadic => {
+                    let mut d = error(span, format!("'{}' 的多余参数，将被忽略", command.to_str()));
+                    d.severity = diagnostic::Severity::Warning;
+                    diagnostics.push(d);
+                }
+                None => {}
+            }
+        }
+    }
+
+    // 检查等待的参数: [时长]、[元素, 状态?, 超时?, 间隔?] 或 [界面稳定, 次数?, 超时?, 间隔?]
+    fn check_wait(
+        params: &[TksParam],
+        spans: &[&str],
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        use diagnostic::ParamKind;
+
+        let Some(target) = params.first() else {
+            return;
+        };
+        if !ParamKind::WaitTarget.accepts(target) {
+            diagnostics.push(error(spans[0], format!("参数1 (时长或元素) 应为{}", ParamKind::WaitTarget.describe())));
+            return;
+        }
+
+        // 固定时长之后的参数会被忽略；元素和界面稳定之后可以有状态/次数，再跟超时和间隔
+        let is_element = matches!(target, TksParam::XmlElement { .. } | TksParam::ImageElement(_));
+        let is_ui_idle = matches!(target, TksParam::Text(t) if KeywordTable::is_ui_idle(t));
+        let timing_start = match params.get(1) {
+            Some(TksParam::Boolean(_) | TksParam::Visibility(_)) if is_element => 2,
+            Some(TksParam::Number(count)) if is_ui_idle => {
+                if *count < 2 {
+                    diagnostics.push(error(spans[1], "界面稳定的连续次数至少为2".to_string()));
+                }
+                2
+            }
+            _ => 1,
+        };
+        let timing_end = if is_element || is_ui_idle { timing_start + 2 } else { 1 };
+
+        for (index, (param, span)) in params.iter().zip(spans).enumerate().skip(timing_start) {
+            if index >= timing_end {
+                let mut d = error(span, "'等待' 的多余参数，将被忽略".to_string());
+                d.severity = diagnostic::Severity::Warning;
+                diagnostics.push(d);
+            } else if !ParamKind::Duration.accepts(param) {
+                let name = if index == timing_start { "超时" } else { "间隔" };
+                diagnostics.push(error(span, format!("参数{} ({}) 应为{}", index + 1, name, ParamKind::Duration.describe())));
+            }
+        }
+    }
+
+    // 检查块命令的条件: [{元素}, 存在/不存在/可见/不可见] 或 [目标] == 期望文本
+    fn check_condition(
+        &self,
+        params: &[TksParam],
+        spans: &[&str],
+        has_expected: bool,
+        command_span: &str,
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        use diagnostic::ParamKind;
+
+        let Some(target) = params.first() else {
+            diagnostics.push(error(command_span, "条件需要目标元素".to_string()));
+            return;
+        };
+
+        let (target_kind, expected_len) = if has_expected {
+            (ParamKind::Readable, 1)
+        } else {
+            (ParamKind::Element, 2)
+        };
+
+        if !target_kind.accepts(target) {
+            diagnostics.push(error(spans[0], format!("条件目标应为{}", target_kind.describe())));
+        }
+
+        if has_expected {
+            // 坐标区域需要宽度和高度
+            if matches!(target, TksParam::Coordinate(_)) {
+                for (param, span) in params.iter().zip(spans).skip(1).take(2) {
+                    if !ParamKind::Number.accepts(param) {
+                        diagnostics.push(error(span, format!("区域尺寸应为{}", ParamKind::Number.describe())));
+                    }
+                }
+                if params.len() != 3 {
+                    diagnostics.push(error(command_span, "坐标条件需要宽度和高度，如 [{500,300}, 200, 80] == 文本".to_string()));
+                }
+            } else if params.len() > expected_len {
+                diagnostics.push(error(spans[expected_len], "文本条件只需要目标元素".to_string()));
+            }
+            return;
+        }
+
+        match params.get(1) {
+            None => diagnostics.push(error(command_span,
+                "条件需要 存在/不存在/可见/不可见 或 == 期望文本".to_string())),
+            Some(state) if !ParamKind::State.accepts(state) => {
+                diagnostics.push(error(spans[1], format!("条件应为{}", ParamKind::State.describe())));
+            }
+            Some(_) => {}
+        }
+
+        if params.len() > expected_len {
+            diagnostics.push(error(spans[expected_len], "条件的多余参数".to_string()));
+        }
+    }
+
+    // 检查属性条件: 只支持XML元素，文本匹配的正则表达式必须有效
+    fn check_attribute_condition(
+        params: &[TksParam],
+        spans: &[&str],
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let (Some(target), Some(TksParam::Attribute(condition))) = (params.first(), params.get(1)) else {
+            return;
+        };
+
+        if matches!(target, TksParam::ImageElement(_)) {
+            diagnostics.push(error(spans[1], "图片元素不支持属性条件，请使用XML元素".to_string()));
+        }
+        if let AttributeCondition::TextMatches(pattern) = condition {
+            if !pattern.contains("${") && Regex::new(pattern).is_err() {
+                diagnostics.push(error(spans[1], format!("无效的正则表达式 '{}'", pattern)));
+            }
+        }
+    }
+
+    // 检查按键名称是否在键码表中，组合键不能长按
+    fn check_key(
+        params: &[TksParam],
+        spans: &[&str],
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let key = match params.first() {
+            Some(TksParam::Text(key)) if !key.contains("${") => key.clone(),
+            Some(TksParam::Number(code)) => code.to_string(),
+            _ => return,
+        };
+
+        for name in key.split('+').map(|k| k.trim()) {
+            if keycode::lookup(name).is_some() {
+                continue;
+            }
+            let mut d = error(spans[0], format!("未知的按键 '{}'", name));
+            let upper = name.to_ascii_uppercase();
+            let upper = upper.strip_prefix("KEYCODE_").unwrap_or(&upper);
+            if let Some(candidate) = diagnostic::closest_match(upper, keycode::names()) {
+                d.message.push_str(&format!("，是否为 '{}'?", candidate));
+                d = d.with_suggestion(key.replacen(name, candidate, 1));
+            }
+            diagnostics.push(d);
+        }
+
+        if key.contains('+') && params.get(1).is_some() {
+            diagnostics.push(error(spans[1], "组合键不支持长按".to_string()));
+        }
+    }
+
+    // 检查设备命令是否在项目允许列表中，输出正则必须有效
+    fn check_shell(
+        &self,
+        params: &[TksParam],
+        spans: &[&str],
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if let Some(TksParam::Text(command)) = params.first() {
+            if !command.contains("${") {
+                if let Err(message) = self.shell_allowlist.check(command) {
+                    diagnostics.push(error(spans[0], message));
+                }
+            }
+        }
+        if let Some(TksParam::Text(pattern)) = params.get(1) {
+            if !pattern.contains("${") && Regex::new(pattern).is_err() {
+                diagnostics.push(error(spans[1], format!("无效的正则表达式 '{}'", pattern)));
+            }
+        }
+    }
+
+    // 检查 调用 的子脚本能否找到并正确解析（包括循环调用）
+    fn check_callee(
+        &self,
+        step: &TksStep,
+        script_path: Option<&PathBuf>,
+        call_stack: &mut Vec<PathBuf>,
+        spans: &[&str],
+        error: &impl Fn(&str, String) -> Diagnostic,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let target = match step.params.first() {
+            Some(TksParam::Text(path)) if !path.contains("${") => path,
+            Some(_) => {
+                diagnostics.push(error(spans[0], "子脚本路径不支持变量".to_string()));
+                return;
+            }
+            None => return,
+        };
+
+        if self.resolve_script_path(target, script_path).is_none() {
+            diagnostics.push(error(spans[0], format!("找不到子脚本 '{}'", target)));
+            return;
+        }
+
+        let mut probe = step.clone();
+        if let Err(e) = self.load_callee(&mut probe, script_path, call_stack) {
+            let message = match e {
+                TkeError::ScriptParseError(message) => message,
+                e => e.to_string(),
+            };
+            diagnostics.push(error(spans[0], format!("子脚本 '{}' 解析失败: {}", target, message)));
+        }
+    }
+
+    // 收集脚本步骤中的元素引用 {元素名} / @{图片名}（含 ${变量} 的名称在执行时才确定，不收集）
+    pub fn locator_references(&self, content: &str) -> Vec<LocatorReference> {
+        let mut references = Vec::new();
+        let mut in_steps = false;
+
+        for (line_num, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+
+            if self.keywords.step_section(trimmed).is_some() {
+                in_steps = true;
+                continue;
+            }
+            if !in_steps || trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let offset = line.len() - line.trim_start().len();
+            let (body, _) = self.split_modifiers(trimmed);
+            let (body, _) = self.split_expectation(body);
+            let Some((_, params_start, params_str, _)) = Self::split_command(body) else {
+                continue;
+            };
+
+            for (start, raw) in Self::split_parameters(params_str) {
+                let begin = offset + params_start + start;
+                let raw = &line[begin..begin + raw.len()];
+                let (name, image, strategy, relative) = match self.parse_parameter(raw, None) {
+                    TksParam::XmlElement { name, strategy, relative } => (name, false, strategy, relative),
+                    TksParam::ImageElement(name) => (name, true, None, None),
+                    _ => continue,
+                };
+
+                // 相对定位的锚点元素也是引用
+                let (target, anchor) = match relative {
+                    Some(RelativeLocator { anchor: Anchor::Element(anchor), .. }) => {
+                        let (target, anchor_raw) = Self::split_relative(raw);
+                        (target, Some((anchor, anchor_raw)))
+                    }
+                    Some(_) => (Self::split_relative(raw).0, None),
+                    None => (raw, None),
+                };
+
+                let parts = std::iter::once((name, target, image, strategy))
+                    .chain(anchor.map(|(anchor, anchor_raw)| (anchor, anchor_raw, false, None)));
+                for (name, part, image, strategy) in parts {
+                    if name.contains("${") {
+                        continue;
+                    }
+                    let (start, end) = Self::columns(line, part);
+                    references.push(LocatorReference {
+                        line: line_num + 1,
+                        start,
+                        end,
+                        name,
+                        image,
+                        strategy,
+                    });
+                }
+            }
+        }
+
+        references
+    }
+
+    // 计算行内子串的字符列范围（part 必须是 line 的切片）
+    fn columns(line: &str, part: &str) -> (usize, usize) {
+        let start = (part.as_ptr() as usize).saturating_sub(line.as_ptr() as usize).min(line.len());
+        let end = (start + part.len()).min(line.len());
+        (line[..start].chars().count(), line[..end].chars().count())
+    }
+
     // 获取语法高亮信息（用于编辑器）
     pub fn get_syntax_highlights(&self, content: &str) -> Vec<SyntaxHighlight> {
         let mut highlights = Vec::new();
@@ -300,29 +1491,37 @@ impl ScriptParser {
             }
             
             // 关键字
-            if trimmed.starts_with("用例:") || 
-               trimmed.starts_with("脚本名:") ||
-               trimmed == "详情:" ||
-               trimmed == "步骤:" {
+            if let Some(colon) = trimmed.find([':', '：']) {
+                if self.keywords.section(trimmed[..colon].trim()).is_some() {
+                    highlights.push(SyntaxHighlight {
+                        line: line_num,
+                        start: 0,
+                        end: colon + trimmed[colon..].chars().next().map_or(1, |c| c.len_utf8()),
+                        token_type: TokenType::Keyword,
+                    });
+                }
+            }
+            
+            // 块结构关键字
+            if self.keywords.block(trimmed).is_some() {
                 highlights.push(SyntaxHighlight {
                     line: line_num,
                     start: 0,
-                    end: trimmed.find(':').unwrap_or(trimmed.len()) + 1,
+                    end: trimmed.len(),
                     token_type: TokenType::Keyword,
                 });
+                continue;
             }
-            
+
             // 命令
-            for (cmd_name, _) in &self.command_map {
-                if trimmed.starts_with(cmd_name) {
-                    highlights.push(SyntaxHighlight {
-                        line: line_num,
-                        start: 0,
-                        end: cmd_name.len(),
-                        token_type: TokenType::Command,
-                    });
-                    break;
-                }
+            let word = trimmed.split(|c: char| c.is_whitespace() || c == '[').next().unwrap_or("");
+            if self.keywords.command(word).is_some() {
+                highlights.push(SyntaxHighlight {
+                    line: line_num,
+                    start: 0,
+                    end: word.len(),
+                    token_type: TokenType::Command,
+                });
             }
         }
         
@@ -330,6 +1529,17 @@ impl ScriptParser {
     }
 }
 
+// 脚本中的元素引用（line 从1开始，start/end 为行内字符列）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LocatorReference {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+    pub image: bool,                // 是否为图片元素 @{图片名}
+    pub strategy: Option<String>,   // {元素名}&策略 中的策略
+}
+
 // 语法高亮信息
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SyntaxHighlight {
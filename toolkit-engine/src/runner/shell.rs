@@ -0,0 +1,155 @@
+// 设备命令允许列表 - 执行命令 步骤只能运行项目 shell_allowlist.json 中允许的命令
+// - 文件内容: {"commands": ["settings get", "dumpsys battery", "ls", "grep"]}
+// - 每一项是命令前缀，按单词匹配（"settings get" 允许 settings get global xxx，不允许 settings put）
+// - 允许用 | 连接多个命令，每一段都必须在允许列表中；不允许 ; & > < ` $( 和换行
+// - 项目没有该文件时不允许执行任何命令
+
+use crate::{Result, TkeError};
+use serde::Deserialize;
+use std::path::Path;
+
+// 项目级命令允许列表文件（相对项目目录）
+pub const SHELL_ALLOWLIST_FILE: &str = "shell_allowlist.json";
+
+// 不允许出现在命令中的字符（避免在允许的命令后拼接其他命令或写文件）
+const FORBIDDEN_PATTERNS: [&str; 7] = [";", "&", ">", "<", "`", "$(", "\n"];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AllowListFile {
+    commands: Vec<String>,
+}
+
+// 命令允许列表
+#[derive(Debug, Clone, Default)]
+pub struct ShellAllowList {
+    prefixes: Vec<Vec<String>>,  // 每个允许的命令前缀拆分后的单词
+    loaded: bool,                // 项目中是否存在允许列表文件
+}
+
+impl ShellAllowList {
+    // 加载项目目录下的 shell_allowlist.json（文件不存在时不允许任何命令）
+    pub fn load_project(project_path: &Path) -> Result<Self> {
+        let path = project_path.join(SHELL_ALLOWLIST_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(TkeError::IoError)?;
+        let file: AllowListFile = serde_json::from_str(&content)?;
+
+        let prefixes: Vec<Vec<String>> = file.commands.iter()
+            .map(|command| command.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+            .filter(|words| !words.is_empty())
+            .collect();
+        if let Some(command) = file.commands.iter().find(|c| FORBIDDEN_PATTERNS.iter().any(|p| c.contains(p))) {
+            return Err(TkeError::InvalidArgument(
+                format!("{}: 允许的命令 '{}' 不能包含 ; & > < ` $( 或换行", SHELL_ALLOWLIST_FILE, command)
+            ));
+        }
+
+        Ok(Self { prefixes, loaded: true })
+    }
+
+    // 检查命令是否允许执行，不允许时返回原因
+    pub fn check(&self, command: &str) -> std::result::Result<(), String> {
+        if command.trim().is_empty() {
+            return Err("命令不能为空".to_string());
+        }
+        if let Some(pattern) = FORBIDDEN_PATTERNS.iter().find(|p| command.contains(*p)) {
+            return Err(format!("命令不能包含 '{}'", pattern.escape_default()));
+        }
+        if !self.loaded {
+            return Err(format!("项目中没有 {}，不允许执行设备命令", SHELL_ALLOWLIST_FILE));
+        }
+
+        for segment in command.split('|') {
+            let words: Vec<&str> = segment.split_whitespace().collect();
+            let allowed = self.prefixes.iter()
+                .any(|prefix| words.len() >= prefix.len() && prefix.iter().zip(&words).all(|(a, b)| a == b));
+            if !allowed {
+                return Err(format!("命令 '{}' 不在 {} 的允许列表中", segment.trim(), SHELL_ALLOWLIST_FILE));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 在临时项目目录中写入允许列表并加载
+    fn load(name: &str, content: &str) -> Result<ShellAllowList> {
+        let dir = std::env::temp_dir().join(format!("tke_shell_allowlist_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(SHELL_ALLOWLIST_FILE), content).unwrap();
+        let result = ShellAllowList::load_project(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    fn allow(commands: &[&str]) -> ShellAllowList {
+        let content = serde_json::json!({ "commands": commands }).to_string();
+        load(&commands.join("_").replace(' ', "-"), &content).unwrap()
+    }
+
+    #[test]
+    fn missing_file_allows_nothing() {
+        let list = ShellAllowList::load_project(&PathBuf::from("/nonexistent/tke/project")).unwrap();
+        assert!(list.check("ls").is_err());
+        assert!(ShellAllowList::default().check("settings get global x").is_err());
+    }
+
+    #[test]
+    fn matches_whole_word_prefixes() {
+        let list = allow(&["pm list", "settings get", "ls"]);
+        assert!(list.check("pm list packages").is_ok());
+        assert!(list.check("pm list").is_ok());
+        assert!(list.check("  settings   get global airplane_mode_on ").is_ok());
+        assert!(list.check("ls /sdcard").is_ok());
+
+        assert!(list.check("pmx list packages").is_err());
+        assert!(list.check("pm listx").is_err());
+        assert!(list.check("pm install x.apk").is_err());
+        assert!(list.check("pm").is_err());
+        assert!(list.check("settings put global x 1").is_err());
+        assert!(list.check("lsof").is_err());
+    }
+
+    #[test]
+    fn rejects_forbidden_characters() {
+        let list = allow(&["ls", "echo"]);
+        for command in ["ls; reboot", "ls && reboot", "ls & reboot", "echo x > /sdcard/f", "ls < /sdcard/f",
+                        "echo `reboot`", "echo $(reboot)", "ls\nreboot"] {
+            assert!(list.check(command).is_err(), "应拒绝: {:?}", command);
+        }
+        assert!(list.check("echo $HOME").is_ok());
+    }
+
+    #[test]
+    fn checks_every_pipe_segment() {
+        let list = allow(&["dumpsys battery", "grep"]);
+        assert!(list.check("dumpsys battery | grep level").is_ok());
+        assert!(list.check("dumpsys battery|grep level").is_ok());
+        assert!(list.check("dumpsys battery | sh").is_err());
+        assert!(list.check("grep x | reboot").is_err());
+        assert!(list.check("dumpsys battery |").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_commands() {
+        let list = allow(&["ls"]);
+        assert!(list.check("").is_err());
+        assert!(list.check("   ").is_err());
+    }
+
+    #[test]
+    fn rejects_forbidden_characters_in_allow_list() {
+        assert!(load("forbidden", r#"{"commands": ["ls; reboot"]}"#).is_err());
+        assert!(load("invalid_json", "not json").is_err());
+    }
+}